@@ -0,0 +1,131 @@
+//! Encode, decode and [`qoi::pixels`] iteration throughput plus compression ratio on generated
+//! images, for catching regressions in op selection. Run with `cargo bench --bench codec`; the
+//! `reference` benchmark compares against `qoi.h` on real images.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use qoi::{Channel, Colorspace, QoiHeader};
+
+const WIDTH: u32 = 1024;
+const HEIGHT: u32 = 1024;
+
+/// How long each measurement keeps repeating its closure.
+const MEASURE_FOR: Duration = Duration::from_millis(500);
+
+/// Color of the pixel at `(x, y)`; the alpha is dropped for RGB images.
+type Generator = fn(u32, u32) -> [u8; 4];
+
+fn main() {
+    let images: [(&str, Channel, Generator); 6] = [
+        ("flat", Channel::RGB, flat),
+        ("gradient", Channel::RGB, gradient),
+        ("noise", Channel::RGB, noise),
+        ("smooth", Channel::RGB, smooth),
+        ("smooth_rgba", Channel::RGBA, smooth),
+        ("alpha", Channel::RGBA, alpha),
+    ];
+
+    println!(
+        "{:<12} {:>12} {:>12} {:>12} {:>8}",
+        "image", "encode MB/s", "decode MB/s", "pixels MB/s", "size/raw"
+    );
+    for (name, channels, pixel) in images {
+        let header = QoiHeader::new(WIDTH, HEIGHT, channels, Colorspace::SRGB);
+        let pixels = generate(&header, pixel);
+        let bytes = qoi::encode(&pixels, &header).expect("generated image should encode");
+        let mut out = vec![0; pixels.len()];
+
+        let encode = time(|| {
+            black_box(qoi::encode(black_box(&pixels), &header).unwrap());
+        });
+        let decode = time(|| {
+            qoi::decode_into(black_box(&bytes), &mut out).unwrap();
+        });
+        assert_eq!(out, pixels, "{name} should round trip");
+        let iterate = time(|| {
+            black_box(qoi::pixels(black_box(&bytes)).unwrap().count());
+        });
+
+        println!(
+            "{name:<12} {:>12.1} {:>12.1} {:>12.1} {:>7.2}%",
+            throughput(pixels.len(), encode),
+            throughput(pixels.len(), decode),
+            throughput(pixels.len(), iterate),
+            bytes.len() as f64 / pixels.len() as f64 * 100.0
+        );
+    }
+}
+
+/// Average duration of `f` over at least [`MEASURE_FOR`] of runs.
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < MEASURE_FOR {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+/// Raw pixel megabytes processed per second.
+fn throughput(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64() / 1e6
+}
+
+fn generate(header: &QoiHeader, pixel: Generator) -> Vec<u8> {
+    let channels = header.channels.bytes_per_pixel();
+    let mut pixels = Vec::with_capacity(header.pixel_count() * channels);
+    for y in 0..header.height {
+        for x in 0..header.width {
+            pixels.extend_from_slice(&pixel(x, y)[..channels]);
+        }
+    }
+    pixels
+}
+
+/// Deterministic per-position noise, so every run sees the same image.
+fn hash(x: u32, y: u32) -> u32 {
+    let mut h = x.wrapping_mul(0x9E37_79B1) ^ y.wrapping_mul(0x85EB_CA77);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2C1B_3C6D);
+    h ^ h >> 12
+}
+
+/// A single color, which encodes to nothing but runs.
+fn flat(_: u32, _: u32) -> [u8; 4] {
+    [0x40, 0x80, 0xC0, 0xFF]
+}
+
+/// Slow ramps in every channel, mostly DIFF and LUMA ops.
+fn gradient(x: u32, y: u32) -> [u8; 4] {
+    [(x / 4) as u8, (y / 4) as u8, ((x + y) / 8) as u8, 0xFF]
+}
+
+/// Uniform noise, the worst case: almost every pixel is a full RGB op.
+fn noise(x: u32, y: u32) -> [u8; 4] {
+    let [r, g, b, _] = hash(x, y).to_le_bytes();
+    [r, g, b, 0xFF]
+}
+
+/// A gradient with a little per-pixel noise, loosely resembling a photograph.
+fn smooth(x: u32, y: u32) -> [u8; 4] {
+    let [r, g, b, _] = gradient(x, y);
+    let noise = (hash(x, y) % 5) as u8;
+    [r.wrapping_add(noise), g, b.wrapping_add(noise / 2), 0xFF]
+}
+
+/// Opaque shapes over a transparent background with soft edges, like a sprite sheet.
+fn alpha(x: u32, y: u32) -> [u8; 4] {
+    let (cx, cy) = (x % 128, y % 128);
+    let distance = cx.abs_diff(64).max(cy.abs_diff(64));
+    let a = match distance {
+        0..=39 => 0xFF,
+        40..=47 => (0xFF - (distance - 39) * 28) as u8,
+        _ => 0,
+    };
+    if a == 0 {
+        return [0, 0, 0, 0];
+    }
+    [(x / 8) as u8, 0x60, (y / 8) as u8, a]
+}
@@ -0,0 +1,4 @@
+// The reference implementation, compiled by build.rs for the `reference` benchmark.
+#define QOI_NO_STDIO
+#define QOI_IMPLEMENTATION
+#include "qoi.h"
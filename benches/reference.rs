@@ -0,0 +1,180 @@
+//! Encode and decode throughput next to the reference `qoi.h` on a directory of `.qoi` images,
+//! such as the standard test images from <https://qoiformat.org/qoi_test_images.zip>. Every image
+//! is also checked to encode to the same bytes and decode to the same pixels with both codecs.
+//!
+//! Needs `qoi.h` and a C compiler, see `build.rs`. Run with
+//! `QOI_IMAGES=path/to/images cargo bench --features reference --bench reference`.
+
+use std::ffi::{c_int, c_uint, c_void};
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{fs, io};
+
+use qoi::QoiHeader;
+
+/// How long each measurement keeps repeating its closure. Shorter than in the `codec` benchmark
+/// because the test set has hundreds of images.
+const MEASURE_FOR: Duration = Duration::from_millis(100);
+
+#[repr(C)]
+struct QoiDesc {
+    width: c_uint,
+    height: c_uint,
+    channels: u8,
+    colorspace: u8,
+}
+
+extern "C" {
+    fn qoi_encode(data: *const c_void, desc: *const QoiDesc, out_len: *mut c_int) -> *mut c_void;
+    fn qoi_decode(
+        data: *const c_void,
+        size: c_int,
+        desc: *mut QoiDesc,
+        channels: c_int,
+    ) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+/// A buffer returned by `qoi.h`, freed on drop.
+struct Malloced {
+    ptr: *mut c_void,
+    len: usize,
+}
+
+impl Malloced {
+    fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr.cast(), self.len) }
+    }
+}
+
+impl Drop for Malloced {
+    fn drop(&mut self) {
+        unsafe { free(self.ptr) }
+    }
+}
+
+fn reference_encode(pixels: &[u8], header: &QoiHeader) -> Malloced {
+    let desc = QoiDesc {
+        width: header.width,
+        height: header.height,
+        channels: header.channels.bytes_per_pixel() as u8,
+        colorspace: header.colorspace as u8,
+    };
+    let mut len = 0;
+    let ptr = unsafe { qoi_encode(pixels.as_ptr().cast(), &desc, &mut len) };
+    assert!(!ptr.is_null(), "qoi.h failed to encode");
+    Malloced {
+        ptr,
+        len: len as usize,
+    }
+}
+
+fn reference_decode(bytes: &[u8]) -> Malloced {
+    let mut desc = QoiDesc {
+        width: 0,
+        height: 0,
+        channels: 0,
+        colorspace: 0,
+    };
+    let ptr = unsafe { qoi_decode(bytes.as_ptr().cast(), bytes.len() as c_int, &mut desc, 0) };
+    assert!(!ptr.is_null(), "qoi.h failed to decode");
+    Malloced {
+        ptr,
+        len: desc.width as usize * desc.height as usize * desc.channels as usize,
+    }
+}
+
+fn main() -> io::Result<()> {
+    let dir = std::env::var_os("QOI_IMAGES")
+        .expect("set QOI_IMAGES to a directory of .qoi images, e.g. the QOI test image set");
+    let mut paths = Vec::new();
+    find_images(Path::new(&dir), &mut paths)?;
+    paths.sort();
+
+    println!(
+        "{:<32} {:>12} {:>12} {:>12} {:>12}",
+        "image", "encode MB/s", "qoi.h", "decode MB/s", "qoi.h"
+    );
+    // raw bytes processed and time taken by each column, for the totals
+    let mut raw = 0;
+    let mut totals = [Duration::ZERO; 4];
+    for path in &paths {
+        let file = fs::read(path)?;
+        let (header, pixels) = qoi::decode(&file).expect("test image should decode");
+        let bytes = qoi::encode(&pixels, &header).unwrap();
+        let name = path.strip_prefix(&dir).unwrap_or(path).display();
+        assert_eq!(
+            reference_encode(&pixels, &header).as_slice(),
+            bytes,
+            "{name} should encode like qoi.h"
+        );
+        assert_eq!(
+            reference_decode(&bytes).as_slice(),
+            pixels,
+            "{name} should decode like qoi.h"
+        );
+
+        let times = [
+            time(|| drop(black_box(qoi::encode(black_box(&pixels), &header).unwrap()))),
+            time(|| drop(black_box(reference_encode(black_box(&pixels), &header)))),
+            time(|| drop(black_box(qoi::decode(black_box(&bytes)).unwrap()))),
+            time(|| drop(black_box(reference_decode(black_box(&bytes))))),
+        ];
+        println!(
+            "{name:<32} {:>12.1} {:>12.1} {:>12.1} {:>12.1}",
+            throughput(pixels.len(), times[0]),
+            throughput(pixels.len(), times[1]),
+            throughput(pixels.len(), times[2]),
+            throughput(pixels.len(), times[3]),
+        );
+        raw += pixels.len();
+        for (total, time) in totals.iter_mut().zip(times) {
+            *total += time;
+        }
+    }
+
+    println!(
+        "{:<32} {:>12.1} {:>12.1} {:>12.1} {:>12.1}",
+        format!("total ({} images)", paths.len()),
+        throughput(raw, totals[0]),
+        throughput(raw, totals[1]),
+        throughput(raw, totals[2]),
+        throughput(raw, totals[3]),
+    );
+    println!(
+        "relative to qoi.h: encode {:.0}%, decode {:.0}%",
+        totals[1].as_secs_f64() / totals[0].as_secs_f64() * 100.0,
+        totals[3].as_secs_f64() / totals[2].as_secs_f64() * 100.0,
+    );
+    Ok(())
+}
+
+/// Collect the `.qoi` files under `dir`, including those in subdirectories.
+fn find_images(dir: &Path, paths: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            find_images(&path, paths)?;
+        } else if path.extension().is_some_and(|extension| extension == "qoi") {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Average duration of `f` over at least [`MEASURE_FOR`] of runs.
+fn time(mut f: impl FnMut()) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < MEASURE_FOR {
+        f();
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+/// Raw pixel megabytes processed per second.
+fn throughput(bytes: usize, elapsed: Duration) -> f64 {
+    bytes as f64 / elapsed.as_secs_f64() / 1e6
+}
@@ -0,0 +1,35 @@
+//! Compiles the reference `qoi.h` for `benches/reference.rs` when the `reference` feature is on;
+//! otherwise there is nothing to build.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "reference")]
+    reference();
+}
+
+/// `qoi.h` is looked up in `QOI_H_DIR`, or next to this file if that is unset.
+#[cfg(feature = "reference")]
+fn reference() {
+    use std::path::PathBuf;
+
+    println!("cargo:rerun-if-env-changed=QOI_H_DIR");
+    println!("cargo:rerun-if-changed=benches/qoi_h.c");
+    let dir = std::env::var_os("QOI_H_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(env!("CARGO_MANIFEST_DIR")));
+    let header = dir.join("qoi.h");
+    if !header.is_file() {
+        panic!(
+            "{} not found: copy qoi.h from https://github.com/phoboslab/qoi there or point \
+             QOI_H_DIR at a checkout",
+            header.display()
+        );
+    }
+    println!("cargo:rerun-if-changed={}", header.display());
+
+    cc::Build::new()
+        .file("benches/qoi_h.c")
+        .include(&dir)
+        .opt_level(3)
+        .compile("qoi_h");
+}
@@ -0,0 +1,256 @@
+use alloc::vec::Vec;
+
+use crate::{
+    Channel, Colorspace, QoiError, QoiHeader, QoiOpDiff, QoiOpIndex, QoiOpLuma, QoiOpRGB,
+    QoiOpRGBA, QoiOpRun, QoiOps, Result, QOI_END_MARKER, RGBA,
+};
+
+/// Assemble the text form of a stream, as printed by `qoi dump`, into `.qoi` bytes.
+///
+/// The first line is the header, `QOIF width height channels colorspace`, followed by one op per
+/// line in the form written by the `Display` impl of [`QoiOps`]:
+///
+/// ```text
+/// QOIF 4 1 4 srgb
+/// RGBA 255 0 0 255        # everything after a `#` is a comment
+/// DIFF dr=-1 dg=1 db=0
+/// LUMA dg=-3 dr=1 db=0    # dr - dg and db - dg must fit in -8..7
+/// RUN 1
+/// ```
+///
+/// `BYTES` copies raw byte values into the stream and `END` writes the end marker, which is
+/// otherwise appended after the last line. Only the syntax and the range of each field are
+/// checked, so the ops do not have to add up to the pixel count in the header; this is meant for
+/// crafting edge cases to feed to decoders.
+pub fn assemble(text: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut has_header = false;
+    let mut ended = false;
+
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(mnemonic) = tokens.next() else {
+            continue;
+        };
+        let error = |reason| QoiError::InvalidAssembly {
+            line: number + 1,
+            reason,
+        };
+
+        if !has_header {
+            if !mnemonic.eq_ignore_ascii_case("QOIF") {
+                return Err(error("expected a QOIF header line"));
+            }
+            let header = parse_header(&mut tokens).map_err(error)?;
+            bytes.extend_from_slice(&header.to_bytes());
+            has_header = true;
+            if tokens.next().is_some() {
+                return Err(error("unexpected trailing operand"));
+            }
+            continue;
+        }
+
+        let mnemonic = mnemonic.to_ascii_uppercase();
+        match mnemonic.as_str() {
+            "END" => {
+                bytes.extend_from_slice(&QOI_END_MARKER);
+                ended = true;
+            }
+            "BYTES" => {
+                for token in tokens.by_ref() {
+                    bytes.push(number_in(token, 0, 255).map_err(error)? as u8);
+                }
+            }
+            _ => {
+                let op = parse_op(&mnemonic, &mut tokens).map_err(error)?;
+                let mut buf = [0; 5];
+                let size = op.write_bytes(&mut buf);
+                bytes.extend_from_slice(&buf[..size]);
+            }
+        }
+        if tokens.next().is_some() {
+            return Err(error("unexpected trailing operand"));
+        }
+    }
+
+    if !has_header {
+        return Err(QoiError::InvalidAssembly {
+            line: text.lines().count().max(1),
+            reason: "missing QOIF header line",
+        });
+    }
+    if !ended {
+        bytes.extend_from_slice(&QOI_END_MARKER);
+    }
+    Ok(bytes)
+}
+
+type Parsed<T> = core::result::Result<T, &'static str>;
+
+fn parse_header<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Parsed<QoiHeader> {
+    let mut next = || tokens.next().ok_or("missing header field");
+    let width = number_in(next()?, 0, u32::MAX as i64)? as u32;
+    let height = number_in(next()?, 0, u32::MAX as i64)? as u32;
+    let channels = match next()?.to_ascii_lowercase().as_str() {
+        "3" | "rgb" => Channel::RGB,
+        "4" | "rgba" => Channel::RGBA,
+        _ => return Err("channels must be 3 or 4"),
+    };
+    let colorspace = match next()?.to_ascii_lowercase().as_str() {
+        "0" | "srgb" => Colorspace::SRGB,
+        "1" | "linear" => Colorspace::Linear,
+        _ => return Err("colorspace must be srgb or linear"),
+    };
+    Ok(QoiHeader::new(width, height, channels, colorspace))
+}
+
+fn parse_op<'a>(mnemonic: &str, tokens: &mut impl Iterator<Item = &'a str>) -> Parsed<QoiOps> {
+    let mut value = |min, max| number_in(tokens.next().ok_or("missing operand")?, min, max);
+    let op = match mnemonic {
+        "RUN" => QoiOps::Run(
+            QoiOpRun::with_run(value(0, 255)? as u8).ok_or("RUN length must be in 1..62")?,
+        ),
+        "INDEX" => {
+            QoiOps::Index(QoiOpIndex::new(value(0, 255)? as u8).ok_or("INDEX must be in 0..63")?)
+        }
+        "RGB" => {
+            let [r, g, b] = [value(0, 255)?, value(0, 255)?, value(0, 255)?].map(|v| v as u8);
+            QoiOps::RGB(QoiOpRGB::from_rgba(&RGBA { r, g, b, a: 0xFF }))
+        }
+        "RGBA" => {
+            let [r, g, b, a] = [
+                value(0, 255)?,
+                value(0, 255)?,
+                value(0, 255)?,
+                value(0, 255)?,
+            ]
+            .map(|v| v as u8);
+            QoiOps::RGBA(QoiOpRGBA::from_rgba(&RGBA { r, g, b, a }))
+        }
+        "DIFF" => QoiOps::Diff(
+            QoiOpDiff::from_differences(differences(tokens)?)
+                .ok_or("DIFF differences must be in -2..1")?,
+        ),
+        "LUMA" => QoiOps::Luma(
+            QoiOpLuma::from_differences(differences(tokens)?)
+                .ok_or("LUMA dg must be in -32..31, dr - dg and db - dg in -8..7")?,
+        ),
+        _ => return Err("unknown mnemonic"),
+    };
+    Ok(op)
+}
+
+/// Read the `dr=`, `dg=` and `db=` operands of DIFF and LUMA, in any order.
+fn differences<'a>(tokens: &mut impl Iterator<Item = &'a str>) -> Parsed<[i8; 3]> {
+    let mut values = [None; 3];
+    for _ in 0..3 {
+        let token = tokens.next().ok_or("missing operand")?;
+        let (key, value) = token.split_once('=').ok_or("expected key=value")?;
+        let slot = match key.to_ascii_lowercase().as_str() {
+            "dr" => 0,
+            "dg" => 1,
+            "db" => 2,
+            _ => return Err("expected dr=, dg= or db="),
+        };
+        if values[slot].is_some() {
+            return Err("repeated operand");
+        }
+        values[slot] = Some(number_in(value, -128, 127)? as i8);
+    }
+    let [Some(dr), Some(dg), Some(db)] = values else {
+        return Err("missing operand");
+    };
+    Ok([dr, dg, db])
+}
+
+/// Parse a decimal or `0x` hexadecimal integer and check that it lies in `min..=max`.
+fn number_in(token: &str, min: i64, max: i64) -> Parsed<i64> {
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, token),
+    };
+    let (radix, digits) = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => (16, hex),
+        None => (10, digits),
+    };
+    // `from_str_radix` takes a sign of its own, which would let `--5` or `0x+5` through
+    if !digits.starts_with(|c: char| c.is_digit(radix)) {
+        return Err("expected a number");
+    }
+    let magnitude = i64::from_str_radix(digits, radix).map_err(|_| "expected a number")?;
+    let value = if negative { -magnitude } else { magnitude };
+    if !(min..=max).contains(&value) {
+        return Err("operand out of range");
+    }
+    Ok(value)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_numbers() {
+        assert_eq!(number_in("5", -10, 10), Ok(5));
+        assert_eq!(number_in("-5", -10, 10), Ok(-5));
+        assert_eq!(number_in("0x1F", 0, 255), Ok(31));
+        assert_eq!(number_in("-0x10", -128, 127), Ok(-16));
+        assert_eq!(number_in("256", 0, 255), Err("operand out of range"));
+        for token in [
+            "", "-", "--5", "-+5", "+5", "0x", "0x-5", "0x+5", "-0x-5", "5x",
+        ] {
+            assert_eq!(
+                number_in(token, -10, 10),
+                Err("expected a number"),
+                "{token:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn assembles_ops() {
+        let bytes = assemble(
+            "QOIF 4 1 rgba srgb\n\
+             RGBA 255 0 0 255   # red\n\
+             diff DR=-1 dg=1 db=0\n\
+             LUMA db=0 dr=1 dg=-3\n\
+             RUN 1\n",
+        )
+        .unwrap();
+        let mut expected = QoiHeader::new(4, 1, Channel::RGBA, Colorspace::SRGB)
+            .to_bytes()
+            .to_vec();
+        expected.extend_from_slice(&[0xFF, 255, 0, 0, 255, 0x5E, 0x9D, 0xCB, 0xC0]);
+        expected.extend_from_slice(&QOI_END_MARKER);
+        assert_eq!(bytes, expected);
+    }
+
+    #[test]
+    fn reports_the_failing_line() {
+        let error = assemble("QOIF 1 1 3 srgb\nRUN 1\nDIFF dr=--1 dg=0 db=0\n").unwrap_err();
+        assert!(matches!(
+            error,
+            QoiError::InvalidAssembly {
+                line: 3,
+                reason: "expected a number"
+            }
+        ));
+
+        for (text, line) in [
+            ("QOIF 1 1 3 srgb extra\n", 1),
+            ("QOIF 1 1 3 srgb\nRUN 1 2\n", 2),
+        ] {
+            assert!(matches!(
+                assemble(text),
+                Err(QoiError::InvalidAssembly {
+                    line: l,
+                    reason: "unexpected trailing operand"
+                }) if l == line
+            ));
+        }
+    }
+}
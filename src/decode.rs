@@ -0,0 +1,783 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::{self, Read};
+
+use crate::pixel::hash;
+use crate::{
+    Channel, Encountered, QoiError, QoiHeader, QoiOps, Result, Tag, QOI_END_MARKER,
+    QOI_HEADER_SIZE, RGBA,
+};
+
+/// Decode a `.qoi` byte stream into its header and tightly packed pixels, with 3 or 4 bytes per
+/// pixel as given by `header.channels`.
+#[cfg(feature = "alloc")]
+pub fn decode(bytes: &[u8]) -> Result<(QoiHeader, Vec<u8>)> {
+    let header = QoiHeader::peek(bytes)?;
+    let mut pixels = vec![0; header.pixel_count() * header.channels.bytes_per_pixel()];
+    decode_into(bytes, &mut pixels)?;
+    Ok((header, pixels))
+}
+
+/// Decode into a caller-provided buffer of exactly `width * height * channels` bytes, with the
+/// channel count taken from the header. Use [`QoiHeader::peek`] to size the buffer.
+pub fn decode_into(bytes: &[u8], out: &mut [u8]) -> Result<QoiHeader> {
+    let header = QoiHeader::peek(bytes)?;
+    decode_into_channels(bytes, out, header.channels)
+}
+
+/// Like [`decode_into`], but writes `channels` bytes per pixel regardless of the header. Alpha is
+/// dropped when decoding to RGB. Decoding to RGBA keeps the alpha the ops produce, like the
+/// reference decoder: for an RGB image that is 255 unless the stream contains RGBA ops anyway.
+pub fn decode_into_channels(bytes: &[u8], out: &mut [u8], channels: Channel) -> Result<QoiHeader> {
+    let header = QoiHeader::peek(bytes)?;
+    let channels = channels.bytes_per_pixel();
+    let expected = header.pixel_count() * channels;
+    if out.len() != expected {
+        return Err(QoiError::InvalidPixelBuffer {
+            expected,
+            actual: out.len(),
+        });
+    }
+
+    let data = &bytes[QOI_HEADER_SIZE..];
+    let ops = &data[..ops_len(data)];
+    let rest = match channels {
+        3 => decode_pixels::<3>(ops, out)?,
+        _ => decode_pixels::<4>(ops, out)?,
+    };
+
+    let end = ops.len() - rest.len();
+    if !data[end..].starts_with(&QOI_END_MARKER) {
+        return Err(QoiError::MissingEndMarker);
+    }
+    Ok(header)
+}
+
+/// Number of bytes at the start of `data`, the stream after its header, that may hold ops. Like
+/// the reference decoder, an end marker closing the stream is never read as ops, so a stream
+/// whose ops fall short is reported as truncated rather than decoding the marker as INDEX ops.
+fn ops_len(data: &[u8]) -> usize {
+    if data.ends_with(&QOI_END_MARKER) {
+        data.len() - QOI_END_MARKER.len()
+    } else {
+        data.len()
+    }
+}
+
+/// The hot loop behind [`decode_into_channels`], writing `N` bytes per pixel. The output is
+/// walked with an iterator, so the only checks left per op are the ones matching the op's bytes
+/// against the remaining input. Returns the input following the last op.
+fn decode_pixels<'a, const N: usize>(mut data: &'a [u8], out: &mut [u8]) -> Result<&'a [u8]> {
+    let mut index = [[0u8; 4]; 64];
+    let mut px = [0, 0, 0, 0xFF];
+    let mut run = 0;
+
+    for pixel in out.chunks_exact_mut(N) {
+        if run > 0 {
+            run -= 1;
+        } else {
+            match *data {
+                [0b11111110, r, g, b, ref rest @ ..] => {
+                    px = [r, g, b, px[3]];
+                    data = rest;
+                }
+                [0b11111111, r, g, b, a, ref rest @ ..] => {
+                    px = [r, g, b, a];
+                    data = rest;
+                }
+                [b1, ref rest @ ..] if b1 >> 6 == 0b00 => {
+                    px = index[b1 as usize];
+                    data = rest;
+                }
+                [b1, ref rest @ ..] if b1 >> 6 == 0b01 => {
+                    // differences are stored with a bias of 2
+                    px[0] = px[0].wrapping_add((b1 >> 4) & 0x03).wrapping_sub(2);
+                    px[1] = px[1].wrapping_add((b1 >> 2) & 0x03).wrapping_sub(2);
+                    px[2] = px[2].wrapping_add(b1 & 0x03).wrapping_sub(2);
+                    data = rest;
+                }
+                [b1, b2, ref rest @ ..] if b1 >> 6 == 0b10 => {
+                    let dg = (b1 & 0x3F).wrapping_sub(32);
+                    px[0] = px[0].wrapping_add(dg).wrapping_add(b2 >> 4).wrapping_sub(8);
+                    px[1] = px[1].wrapping_add(dg);
+                    px[2] = px[2]
+                        .wrapping_add(dg)
+                        .wrapping_add(b2 & 0x0F)
+                        .wrapping_sub(8);
+                    data = rest;
+                }
+                [b1, ref rest @ ..] if b1 >> 6 == 0b11 && b1 < 0b11111110 => {
+                    run = (b1 & 0x3F) as usize;
+                    data = rest;
+                }
+                _ => return Err(QoiError::UnexpectedEof),
+            }
+            index[hash(px)] = px;
+        }
+        pixel.copy_from_slice(&px[..N]);
+    }
+
+    Ok(data)
+}
+
+/// Decoding state carried from one op to the next.
+#[derive(Debug)]
+struct OpDecoder {
+    previous: RGBA,
+    encountered: Encountered,
+    run: usize, // repeats of `previous` still owed by the last run op
+}
+
+impl OpDecoder {
+    fn new() -> Self {
+        Self {
+            previous: RGBA::new(),
+            encountered: Encountered::new(),
+            run: 0,
+        }
+    }
+
+    /// Apply an op and return the first pixel it produces. The remaining pixels of a run are
+    /// handed out by `next_repeat`.
+    fn apply(&mut self, op: &QoiOps) -> RGBA {
+        self.previous = match op {
+            QoiOps::Run(op) => {
+                self.run = op.run as usize - 1;
+                self.previous
+            }
+            QoiOps::Index(op) => self.encountered.get(op.index),
+            QoiOps::Diff(op) => op.apply(&self.previous),
+            QoiOps::Luma(op) => op.apply(&self.previous),
+            QoiOps::RGB(op) => op.apply(&self.previous),
+            QoiOps::RGBA(op) => op.to_rgba(),
+        };
+        self.encountered.set(&self.previous);
+        self.previous
+    }
+
+    fn next_repeat(&mut self) -> Option<RGBA> {
+        if self.run == 0 {
+            return None;
+        }
+        self.run -= 1;
+        Some(self.previous)
+    }
+}
+
+/// Decoder that pulls ops from `R` as rows are requested, so the compressed stream never has to
+/// be held in memory.
+///
+/// Ops are read a few bytes at a time, so wrap unbuffered sources such as files or sockets in a
+/// [`std::io::BufReader`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct QoiDecoder<R: Read> {
+    reader: R,
+    header: QoiHeader,
+    ops: OpDecoder,
+    rows: u32, // rows still to be decoded
+}
+
+#[cfg(feature = "std")]
+impl<R: Read> QoiDecoder<R> {
+    /// Read and validate the header.
+    pub fn new(mut reader: R) -> Result<Self> {
+        let mut bytes = [0; QOI_HEADER_SIZE];
+        read_exact(&mut reader, &mut bytes)?;
+        let header = QoiHeader::from_bytes(&bytes)?;
+        Ok(Self {
+            reader,
+            rows: header.height,
+            header,
+            ops: OpDecoder::new(),
+        })
+    }
+
+    pub fn header(&self) -> &QoiHeader {
+        &self.header
+    }
+
+    /// Number of bytes in one row of decoded pixels.
+    pub fn row_len(&self) -> usize {
+        self.header.width as usize * self.header.channels.bytes_per_pixel()
+    }
+
+    /// Decode the next row into `row`, which must be exactly `row_len` bytes. Returns `false`
+    /// without touching `row` once every row has been read. The end marker is checked as part of
+    /// reading the last row.
+    pub fn read_row(&mut self, row: &mut [u8]) -> Result<bool> {
+        if row.len() != self.row_len() {
+            return Err(QoiError::InvalidPixelBuffer {
+                expected: self.row_len(),
+                actual: row.len(),
+            });
+        }
+        if self.rows == 0 {
+            return Ok(false);
+        }
+
+        let channels = self.header.channels.bytes_per_pixel();
+        for pixel in row.chunks_exact_mut(channels) {
+            let rgba = match self.ops.next_repeat() {
+                Some(rgba) => rgba,
+                None => {
+                    let op = self.read_op()?;
+                    self.ops.apply(&op)
+                }
+            };
+            pixel.copy_from_slice(&<[u8; 4]>::from(rgba)[..channels]);
+        }
+
+        self.rows -= 1;
+        if self.rows == 0 {
+            let mut marker = [0; QOI_END_MARKER.len()];
+            read_exact(&mut self.reader, &mut marker).map_err(|error| match error {
+                QoiError::UnexpectedEof => QoiError::MissingEndMarker,
+                error => error,
+            })?;
+            if marker != QOI_END_MARKER {
+                return Err(QoiError::MissingEndMarker);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Return the reader, positioned after the last byte consumed.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    fn read_op(&mut self) -> Result<QoiOps> {
+        let mut buf = [0; 5];
+        read_exact(&mut self.reader, &mut buf[..1])?;
+        let len = Tag::from_byte(buf[0]).op_len();
+        read_exact(&mut self.reader, &mut buf[1..len])?;
+        let (op, _) = QoiOps::from_bytes(&buf[..len]).expect("the whole op has been read");
+        Ok(op)
+    }
+}
+
+#[cfg(feature = "std")]
+fn read_exact(reader: &mut impl Read, buf: &mut [u8]) -> Result<()> {
+    reader.read_exact(buf).map_err(|error| match error.kind() {
+        io::ErrorKind::UnexpectedEof => QoiError::UnexpectedEof,
+        _ => QoiError::Io(error),
+    })
+}
+
+/// Progress reported by [`QoiPushDecoder::feed`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DecodeStatus {
+    /// All input has been consumed and the image is not complete yet.
+    NeedMoreData,
+    /// Every pixel and the end marker have been decoded. Further input is ignored.
+    Done,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum PushState {
+    Header,
+    Ops,
+    EndMarker,
+    Done,
+}
+
+/// Push-based decoder for input that arrives in fragments, e.g. from a non-blocking socket.
+///
+/// Bytes are handed over with [`feed`](QoiPushDecoder::feed) in chunks of any size; a header, op or
+/// end marker split across two chunks is buffered until it is complete. After an error the
+/// decoder should be discarded.
+#[derive(Debug)]
+pub struct QoiPushDecoder {
+    state: PushState,
+    header: Option<QoiHeader>,
+    ops: OpDecoder,
+    remaining: usize,              // pixels still to be decoded
+    buffer: [u8; QOI_HEADER_SIZE], // incomplete header, op or end marker
+    buffered: usize,
+}
+
+impl QoiPushDecoder {
+    pub fn new() -> Self {
+        Self {
+            state: PushState::Header,
+            header: None,
+            ops: OpDecoder::new(),
+            remaining: 0,
+            buffer: [0; QOI_HEADER_SIZE],
+            buffered: 0,
+        }
+    }
+
+    /// The header, once its 14 bytes have been fed.
+    pub fn header(&self) -> Option<&QoiHeader> {
+        self.header.as_ref()
+    }
+
+    /// Decode as much of `data` as possible, passing every completed pixel to `on_pixel` in
+    /// stream order.
+    pub fn feed(
+        &mut self,
+        mut data: &[u8],
+        mut on_pixel: impl FnMut(RGBA),
+    ) -> Result<DecodeStatus> {
+        loop {
+            match self.state {
+                PushState::Header => {
+                    if !self.fill(&mut data, QOI_HEADER_SIZE) {
+                        return Ok(DecodeStatus::NeedMoreData);
+                    }
+                    let header = QoiHeader::peek(&self.buffer)?;
+                    self.remaining = header.pixel_count();
+                    self.header = Some(header);
+                    self.buffered = 0;
+                    self.state = PushState::Ops;
+                }
+                PushState::Ops => {
+                    while self.remaining > 0 {
+                        let Some(rgba) = self.ops.next_repeat() else {
+                            break;
+                        };
+                        on_pixel(rgba);
+                        self.remaining -= 1;
+                    }
+                    if self.remaining == 0 {
+                        self.state = PushState::EndMarker;
+                        continue;
+                    }
+
+                    let first = match (self.buffered, data.first()) {
+                        (0, None) => return Ok(DecodeStatus::NeedMoreData),
+                        (0, Some(&first)) => first,
+                        _ => self.buffer[0],
+                    };
+                    let len = Tag::from_byte(first).op_len();
+                    if !self.fill(&mut data, len) {
+                        return Ok(DecodeStatus::NeedMoreData);
+                    }
+                    let (op, _) =
+                        QoiOps::from_bytes(&self.buffer[..len]).expect("the whole op is buffered");
+                    self.buffered = 0;
+                    on_pixel(self.ops.apply(&op));
+                    self.remaining -= 1;
+                }
+                PushState::EndMarker => {
+                    if !self.fill(&mut data, QOI_END_MARKER.len()) {
+                        return Ok(DecodeStatus::NeedMoreData);
+                    }
+                    if self.buffer[..QOI_END_MARKER.len()] != QOI_END_MARKER {
+                        return Err(QoiError::MissingEndMarker);
+                    }
+                    self.buffered = 0;
+                    self.state = PushState::Done;
+                }
+                PushState::Done => return Ok(DecodeStatus::Done),
+            }
+        }
+    }
+
+    /// Move bytes from `data` into the buffer until it holds `len` bytes, returning whether it
+    /// does.
+    fn fill(&mut self, data: &mut &[u8], len: usize) -> bool {
+        let take = (len - self.buffered).min(data.len());
+        self.buffer[self.buffered..self.buffered + take].copy_from_slice(&data[..take]);
+        self.buffered += take;
+        *data = &data[take..];
+        self.buffered == len
+    }
+}
+
+impl Default for QoiPushDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lazily decode the pixels of a `.qoi` byte stream, expanding runs into repeated pixels.
+///
+/// ```
+/// # use qoi::{Channel, Colorspace, QoiHeader};
+/// # let header = QoiHeader::new(2, 2, Channel::RGBA, Colorspace::SRGB);
+/// # let mut bytes = [0; 64];
+/// # let len = qoi::encode_into(&[0xFF; 16], &header, &mut bytes)?;
+/// # let bytes = &bytes[..len];
+/// let mut pixels = qoi::pixels(&bytes)?;
+/// let opaque = pixels.by_ref().filter(|pixel| pixel.a == 0xFF).count();
+/// assert!(pixels.is_complete(), "the stream is truncated");
+/// # assert_eq!(opaque, 4);
+/// # Ok::<(), qoi::QoiError>(())
+/// ```
+pub fn pixels(bytes: &[u8]) -> Result<Pixels<'_>> {
+    let header = QoiHeader::peek(bytes)?;
+    let data = &bytes[QOI_HEADER_SIZE..];
+    Ok(Pixels {
+        bytes: &data[..ops_len(data)],
+        remaining: header.pixel_count(),
+        header,
+        ops: OpDecoder::new(),
+    })
+}
+
+/// Iterator returned by [`pixels`].
+///
+/// Iteration stops early if the stream is truncated, so check [`Pixels::is_complete`] once it
+/// has ended before trusting anything computed from the pixels. The end marker is not checked.
+#[derive(Debug)]
+pub struct Pixels<'a> {
+    bytes: &'a [u8], // ops not decoded yet
+    header: QoiHeader,
+    ops: OpDecoder,
+    remaining: usize,
+}
+
+impl Pixels<'_> {
+    pub fn header(&self) -> &QoiHeader {
+        &self.header
+    }
+
+    /// Whether every pixel the header describes has been produced. `false` after iteration has
+    /// ended means the stream was truncated.
+    pub fn is_complete(&self) -> bool {
+        self.remaining == 0
+    }
+}
+
+impl Iterator for Pixels<'_> {
+    type Item = RGBA;
+
+    fn next(&mut self) -> Option<RGBA> {
+        if self.remaining == 0 {
+            return None;
+        }
+        let rgba = match self.ops.next_repeat() {
+            Some(rgba) => rgba,
+            None => {
+                let (op, size) = QoiOps::from_bytes(self.bytes)?;
+                self.bytes = &self.bytes[size..];
+                self.ops.apply(&op)
+            }
+        };
+        self.remaining -= 1;
+        Some(rgba)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.remaining))
+    }
+}
+
+/// Walk the ops of a `.qoi` byte stream together with their position in the stream and in the
+/// image, for tools that inspect how an image was encoded.
+///
+/// Like [`pixels`], iteration stops once the header's pixel count has been produced or the ops
+/// run out, without reading an end marker closing the stream as ops; [`Ops::remainder`] holds
+/// the bytes that follow.
+pub fn ops(bytes: &[u8]) -> Result<Ops<'_>> {
+    let header = QoiHeader::peek(bytes)?;
+    let data = &bytes[QOI_HEADER_SIZE..];
+    Ok(Ops {
+        bytes: data,
+        ops_len: ops_len(data),
+        offset: QOI_HEADER_SIZE,
+        pixel: 0,
+        header,
+        ops: OpDecoder::new(),
+    })
+}
+
+/// An op yielded by [`Ops`].
+#[derive(Debug)]
+pub struct OpInfo {
+    pub op: QoiOps,
+    pub offset: usize, // position of the tag byte, counted from the start of the stream
+    pub size: usize,   // bytes used by the op: 1..=5
+    pub pixel: usize,  // index of the first pixel the op produces, in row-major order
+    pub rgba: RGBA,    // the pixel produced, repeated for every pixel of a run
+}
+
+impl OpInfo {
+    /// Number of pixels the op produces.
+    pub fn pixel_count(&self) -> usize {
+        match &self.op {
+            QoiOps::Run(op) => op.run as usize,
+            _ => 1,
+        }
+    }
+}
+
+/// Iterator returned by [`ops`].
+#[derive(Debug)]
+pub struct Ops<'a> {
+    bytes: &'a [u8], // ops not decoded yet, then everything after them
+    ops_len: usize,  // bytes at the start of `bytes` that may hold ops
+    offset: usize,   // position of `bytes` in the stream
+    pixel: usize,    // pixels produced so far
+    header: QoiHeader,
+    ops: OpDecoder,
+}
+
+impl<'a> Ops<'a> {
+    pub fn header(&self) -> &QoiHeader {
+        &self.header
+    }
+
+    /// Number of pixels produced by the ops yielded so far.
+    pub fn pixels_decoded(&self) -> usize {
+        self.pixel
+    }
+
+    /// The bytes following the ops yielded so far, which is the end marker once iteration has
+    /// finished on a well-formed stream.
+    pub fn remainder(&self) -> &'a [u8] {
+        self.bytes
+    }
+}
+
+impl Iterator for Ops<'_> {
+    type Item = OpInfo;
+
+    fn next(&mut self) -> Option<OpInfo> {
+        if self.pixel >= self.header.pixel_count() {
+            return None;
+        }
+        let (op, size) = QoiOps::from_bytes(&self.bytes[..self.ops_len])?;
+        let rgba = self.ops.apply(&op);
+        let info = OpInfo {
+            op,
+            offset: self.offset,
+            size,
+            pixel: self.pixel,
+            rgba,
+        };
+        self.bytes = &self.bytes[size..];
+        self.ops_len -= size;
+        self.offset += size;
+        self.pixel += info.pixel_count();
+        Some(info)
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{testing, Colorspace};
+
+    fn round_trip(pixels: &[u8], header: &QoiHeader) -> Vec<u8> {
+        let bytes = crate::encode(pixels, header).unwrap();
+        let (decoded_header, decoded) = decode(&bytes).unwrap();
+        assert_eq!(decoded_header.width, header.width);
+        assert_eq!(decoded_header.height, header.height);
+        assert_eq!(decoded_header.channels, header.channels);
+        assert_eq!(decoded, pixels);
+        bytes
+    }
+
+    /// The text form of every op in the stream.
+    fn listing(bytes: &[u8]) -> Vec<String> {
+        ops(bytes)
+            .unwrap()
+            .map(|info| info.op.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn round_trips_generated_images() {
+        for channels in [Channel::RGB, Channel::RGBA] {
+            for (seed, (width, height)) in [(1, 1), (7, 3), (64, 64), (129, 17)]
+                .into_iter()
+                .enumerate()
+            {
+                let header = QoiHeader::new(width, height, channels, Colorspace::SRGB);
+                let pixels = testing::image(width, height, channels, seed as u32);
+                round_trip(&pixels, &header);
+            }
+        }
+
+        // the generator is meant to exercise every op
+        let header = QoiHeader::new(64, 64, Channel::RGBA, Colorspace::SRGB);
+        let bytes = round_trip(&testing::image(64, 64, Channel::RGBA, 0), &header);
+        let listing = listing(&bytes);
+        for op in ["RUN", "INDEX", "DIFF", "LUMA", "RGB", "RGBA"] {
+            assert!(
+                listing
+                    .iter()
+                    .any(|line| line.split(' ').next() == Some(op)),
+                "{op}"
+            );
+        }
+    }
+
+    /// Read every row of `bytes` through a [`QoiDecoder`] and concatenate them.
+    fn read_rows(bytes: &[u8]) -> Result<Vec<u8>> {
+        let mut decoder = QoiDecoder::new(bytes)?;
+        let mut row = vec![0; decoder.row_len()];
+        let mut pixels = Vec::new();
+        while decoder.read_row(&mut row)? {
+            pixels.extend_from_slice(&row);
+        }
+        assert!(decoder.into_inner().is_empty());
+        Ok(pixels)
+    }
+
+    #[test]
+    fn row_decoder_carries_state_across_rows() {
+        for channels in [Channel::RGB, Channel::RGBA] {
+            let header = QoiHeader::new(23, 9, channels, Colorspace::SRGB);
+            let bytes = crate::encode(&testing::image(23, 9, channels, 4), &header).unwrap();
+            let crosses_a_row =
+                |info: &OpInfo| info.pixel / 23 != (info.pixel + info.pixel_count() - 1) / 23;
+            assert!(ops(&bytes).unwrap().any(|info| crosses_a_row(&info)));
+            assert!(listing(&bytes).iter().any(|op| op.starts_with("INDEX ")));
+
+            assert_eq!(read_rows(&bytes).unwrap(), decode(&bytes).unwrap().1);
+        }
+    }
+
+    #[test]
+    fn row_decoder_rejects_bad_input() {
+        let header = QoiHeader::new(16, 16, Channel::RGBA, Colorspace::SRGB);
+        let bytes = crate::encode(&testing::image(16, 16, Channel::RGBA, 6), &header).unwrap();
+
+        let mut decoder = QoiDecoder::new(&bytes[..]).unwrap();
+        assert!(matches!(
+            decoder.read_row(&mut [0; 16 * 3]),
+            Err(QoiError::InvalidPixelBuffer {
+                expected: 64,
+                actual: 48
+            })
+        ));
+
+        assert!(matches!(
+            read_rows(&bytes[..bytes.len() / 2]),
+            Err(QoiError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            read_rows(&bytes[..bytes.len() - 1]),
+            Err(QoiError::MissingEndMarker)
+        ));
+        let mut bad_marker = bytes.clone();
+        *bad_marker.last_mut().unwrap() = 0;
+        assert!(matches!(
+            read_rows(&bad_marker),
+            Err(QoiError::MissingEndMarker)
+        ));
+    }
+
+    /// Feed `bytes` to a push decoder in chunks of the given sizes, cycling through them.
+    fn push_decode(bytes: &[u8], sizes: &[usize]) -> Vec<u8> {
+        let mut decoder = QoiPushDecoder::new();
+        let mut pixels = Vec::new();
+        let mut rest = bytes;
+        let mut status = DecodeStatus::NeedMoreData;
+        for &size in sizes.iter().cycle() {
+            if rest.is_empty() {
+                break;
+            }
+            let (chunk, tail) = rest.split_at(size.min(rest.len()));
+            rest = tail;
+            status = decoder
+                .feed(chunk, |rgba| {
+                    pixels.extend_from_slice(&<[u8; 4]>::from(rgba))
+                })
+                .unwrap();
+        }
+        assert_eq!(status, DecodeStatus::Done);
+        pixels
+    }
+
+    #[test]
+    fn push_decoder_accepts_any_split() {
+        let header = QoiHeader::new(37, 23, Channel::RGBA, Colorspace::SRGB);
+        let bytes = crate::encode(&testing::image(37, 23, Channel::RGBA, 7), &header).unwrap();
+        assert!(listing(&bytes).iter().any(|op| op.starts_with("RGBA ")));
+        let (_, expected) = decode(&bytes).unwrap();
+
+        // one byte at a time splits the header, every multi-byte op and the end marker
+        assert_eq!(push_decode(&bytes, &[1]), expected);
+        assert_eq!(push_decode(&bytes, &[3, 1, 4, 1, 5, 9, 2, 6]), expected);
+        assert_eq!(push_decode(&bytes, &[13, 2, 7, 64, 1]), expected);
+    }
+
+    #[test]
+    fn push_decoder_rejects_a_bad_end_marker() {
+        let header = QoiHeader::new(2, 1, Channel::RGB, Colorspace::SRGB);
+        let mut bytes = crate::encode(&[1, 2, 3, 4, 5, 6], &header).unwrap();
+        *bytes.last_mut().unwrap() = 0;
+        let mut decoder = QoiPushDecoder::new();
+        let result = bytes
+            .iter()
+            .try_for_each(|byte| decoder.feed(&[*byte], |_| {}).map(drop));
+        assert!(matches!(result, Err(QoiError::MissingEndMarker)));
+    }
+
+    #[test]
+    fn pixels_reports_a_truncated_stream() {
+        let header = QoiHeader::new(16, 16, Channel::RGBA, Colorspace::SRGB);
+        let bytes = crate::encode(&testing::image(16, 16, Channel::RGBA, 3), &header).unwrap();
+
+        let mut iter = pixels(&bytes).unwrap();
+        assert_eq!(iter.by_ref().count(), 256);
+        assert!(iter.is_complete());
+
+        let mut iter = pixels(&bytes[..bytes.len() / 2]).unwrap();
+        assert!(iter.by_ref().count() < 256);
+        assert!(!iter.is_complete());
+    }
+
+    #[test]
+    fn pixels_matches_decode() {
+        for channels in [Channel::RGB, Channel::RGBA] {
+            let header = QoiHeader::new(129, 17, channels, Colorspace::SRGB);
+            let bytes = crate::encode(&testing::image(129, 17, channels, 5), &header).unwrap();
+            let iterated: Vec<[u8; 4]> = pixels(&bytes).unwrap().map(<[u8; 4]>::from).collect();
+
+            for out_channels in [Channel::RGB, Channel::RGBA] {
+                let n = out_channels.bytes_per_pixel();
+                let mut out = vec![0; header.pixel_count() * n];
+                decode_into_channels(&bytes, &mut out, out_channels).unwrap();
+                let expected: Vec<u8> = iterated.iter().flat_map(|px| px[..n].to_vec()).collect();
+                assert_eq!(out, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn does_not_read_the_end_marker_as_ops() {
+        // one of two pixels, followed by a correct end marker
+        let header = QoiHeader::new(2, 1, Channel::RGB, Colorspace::SRGB);
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(&[0xFE, 1, 2, 3]);
+        bytes.extend_from_slice(&QOI_END_MARKER);
+
+        for channels in [Channel::RGB, Channel::RGBA] {
+            let mut out = vec![0; 2 * channels.bytes_per_pixel()];
+            let result = decode_into_channels(&bytes, &mut out, channels);
+            assert!(matches!(result, Err(QoiError::UnexpectedEof)));
+        }
+
+        let mut iter = pixels(&bytes).unwrap();
+        assert_eq!(iter.by_ref().count(), 1);
+        assert!(!iter.is_complete());
+
+        let mut ops = ops(&bytes).unwrap();
+        assert_eq!(ops.by_ref().count(), 1);
+        assert_eq!(ops.remainder(), QOI_END_MARKER);
+    }
+
+    #[test]
+    fn decoding_to_rgba_keeps_alpha_from_the_stream() {
+        let header = QoiHeader::new(2, 1, Channel::RGB, Colorspace::SRGB);
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(&[0xFF, 1, 2, 3, 4, 0xFE, 5, 6, 7]);
+        bytes.extend_from_slice(&QOI_END_MARKER);
+
+        let mut rgba = [0; 8];
+        decode_into_channels(&bytes, &mut rgba, Channel::RGBA).unwrap();
+        assert_eq!(rgba, [1, 2, 3, 4, 5, 6, 7, 4]);
+
+        let mut rgb = [0; 6];
+        decode_into(&bytes, &mut rgb).unwrap();
+        assert_eq!(rgb, [1, 2, 3, 5, 6, 7]);
+    }
+}
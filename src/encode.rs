@@ -0,0 +1,473 @@
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::io::Write;
+
+use crate::pixel::hash;
+use crate::{Channel, QoiError, QoiHeader, QoiOpRun, Result, Tag, QOI_END_MARKER, QOI_HEADER_SIZE};
+#[cfg(feature = "alloc")]
+use crate::{
+    Chunks, Encountered, QoiOpDiff, QoiOpIndex, QoiOpLuma, QoiOpRGB, QoiOpRGBA, QoiOps, RGBA,
+};
+
+/// Encode tightly packed RGB or RGBA pixels, as given by `header.channels`, into a `.qoi` byte
+/// stream.
+#[cfg(feature = "alloc")]
+pub fn encode(pixels: &[u8], header: &QoiHeader) -> Result<Vec<u8>> {
+    let channels = check_pixels(pixels, header)?;
+    let mut bytes = vec![0; max_encoded_size(header.width, header.height, header.channels)];
+    let len = write_stream(pixels, header, channels, &mut bytes)?;
+    // the worst case is several times the typical size, so give the excess back
+    bytes.truncate(len);
+    bytes.shrink_to_fit();
+    Ok(bytes)
+}
+
+/// Encode into a caller-provided buffer and return the number of bytes written. A buffer of
+/// [`max_encoded_size`] bytes is always large enough.
+pub fn encode_into(pixels: &[u8], header: &QoiHeader, out: &mut [u8]) -> Result<usize> {
+    let channels = check_pixels(pixels, header)?;
+    write_stream(pixels, header, channels, out)
+}
+
+/// Write the header, ops and end marker for pixels already checked by `check_pixels`.
+fn write_stream(
+    pixels: &[u8],
+    header: &QoiHeader,
+    channels: usize,
+    out: &mut [u8],
+) -> Result<usize> {
+    let mut writer = SliceWriter { out, len: 0 };
+    writer.write(&header.to_bytes())?;
+    match channels {
+        3 => encode_pixels::<3>(pixels, &mut writer)?,
+        _ => encode_pixels::<4>(pixels, &mut writer)?,
+    }
+    writer.write(&QOI_END_MARKER)?;
+
+    Ok(writer.len)
+}
+
+/// The hot loop behind [`encode_into`]. It makes the same choices as `OpEncoder`, but keeps
+/// pixels packed in a `u32` and writes op bytes directly instead of going through `QoiOps`.
+fn encode_pixels<const N: usize>(pixels: &[u8], writer: &mut SliceWriter) -> Result<()> {
+    let mut index = [0u32; 64];
+    let mut previous = u32::from_le_bytes([0, 0, 0, 0xFF]);
+    let mut run = 0u8;
+
+    for pixel in pixels.chunks_exact(N) {
+        let alpha = if N == 4 { pixel[3] } else { 0xFF };
+        let px = u32::from_le_bytes([pixel[0], pixel[1], pixel[2], alpha]);
+        if px == previous {
+            // like qoi.h, leave the index alone during a run; the decoder does store these
+            // pixels, so this only loses an INDEX when a run of the implicit initial pixel opens
+            // the image
+            run += 1;
+            if run == QoiOpRun::MAX_RUN {
+                writer.write(&[Tag::B11.bits() | (run - 1)])?;
+                run = 0;
+            }
+            continue;
+        }
+        if run > 0 {
+            writer.write(&[Tag::B11.bits() | (run - 1)])?;
+            run = 0;
+        }
+
+        let slot = hash(px.to_le_bytes());
+        if index[slot] == px {
+            writer.write(&[Tag::B00.bits() | slot as u8])?;
+        } else {
+            index[slot] = px;
+            let [r, g, b, a] = px.to_le_bytes();
+            let [pr, pg, pb, pa] = previous.to_le_bytes();
+            if a == pa {
+                let dr = r.wrapping_sub(pr) as i8;
+                let dg = g.wrapping_sub(pg) as i8;
+                let db = b.wrapping_sub(pb) as i8;
+                let dr_dg = dr.wrapping_sub(dg);
+                let db_dg = db.wrapping_sub(dg);
+                if (-2..=1).contains(&dr) && (-2..=1).contains(&dg) && (-2..=1).contains(&db) {
+                    let bits = ((dr + 2) as u8) << 4 | ((dg + 2) as u8) << 2 | (db + 2) as u8;
+                    writer.write(&[Tag::B01.bits() | bits])?;
+                } else if (-32..=31).contains(&dg)
+                    && (-8..=7).contains(&dr_dg)
+                    && (-8..=7).contains(&db_dg)
+                {
+                    writer.write(&[
+                        Tag::B10.bits() | (dg + 32) as u8,
+                        ((dr_dg + 8) as u8) << 4 | (db_dg + 8) as u8,
+                    ])?;
+                } else {
+                    writer.write(&[Tag::B11111110.bits(), r, g, b])?;
+                }
+            } else {
+                writer.write(&[Tag::B11111111.bits(), r, g, b, a])?;
+            }
+        }
+        previous = px;
+    }
+
+    if run > 0 {
+        writer.write(&[Tag::B11.bits() | (run - 1)])?;
+    }
+    Ok(())
+}
+
+/// Worst-case size of an encoded image: the header, one tag byte plus the channel values for
+/// every pixel, and the end marker.
+pub fn max_encoded_size(width: u32, height: u32, channels: Channel) -> usize {
+    (width as usize)
+        .saturating_mul(height as usize)
+        .saturating_mul(channels.bytes_per_pixel() + 1)
+        .saturating_add(QOI_HEADER_SIZE + QOI_END_MARKER.len())
+}
+
+/// Choose the op for every pixel without serializing them, which is mostly useful for inspecting
+/// the encoder's decisions. The result serializes to the same bytes as [`encode`].
+#[cfg(feature = "alloc")]
+pub fn encode_chunks(pixels: &[u8], header: &QoiHeader) -> Result<Chunks> {
+    let channels = check_pixels(pixels, header)?;
+
+    let mut chunks = Chunks::new();
+    let mut encoder = OpEncoder::new();
+    for pixel in pixels.chunks_exact(channels) {
+        for op in encoder.push(RGBA::from_bytes(pixel)).into_iter().flatten() {
+            chunks.push(op);
+        }
+    }
+    if let Some(op) = encoder.finish() {
+        chunks.push(op);
+    }
+
+    Ok(chunks)
+}
+
+/// Validate the header and check that `pixels` holds exactly the image it describes, returning
+/// the number of bytes per pixel.
+fn check_pixels(pixels: &[u8], header: &QoiHeader) -> Result<usize> {
+    header.validate()?;
+    let channels = header.channels.bytes_per_pixel();
+    let expected = header.pixel_count() * channels;
+    if pixels.len() != expected {
+        return Err(QoiError::InvalidPixelBuffer {
+            expected,
+            actual: pixels.len(),
+        });
+    }
+    Ok(channels)
+}
+
+/// Bounds-checked cursor over the output of [`encode_into`].
+struct SliceWriter<'a> {
+    out: &'a mut [u8],
+    len: usize, // bytes written so far
+}
+
+impl SliceWriter<'_> {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.len + bytes.len();
+        let len = self.out.len();
+        self.out
+            .get_mut(self.len..end)
+            .ok_or(QoiError::OutputTooSmall { len })?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+/// Op selection state carried from one pixel to the next.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+struct OpEncoder {
+    previous: RGBA,
+    encountered: Encountered,
+    run: Option<QoiOpRun>,
+}
+
+#[cfg(feature = "alloc")]
+impl OpEncoder {
+    fn new() -> Self {
+        Self {
+            previous: RGBA::new(),
+            encountered: Encountered::new(),
+            run: None,
+        }
+    }
+
+    /// Feed the next pixel and return the ops it completes: a pending run that just ended,
+    /// followed by the op for the pixel itself.
+    fn push(&mut self, rgba: RGBA) -> [Option<QoiOps>; 2] {
+        if rgba == self.previous {
+            // the first pixel is compared against the implicit initial pixel, so a run can
+            // start before any op has been emitted
+            match &mut self.run {
+                Some(run) => run.add_run(),
+                None => self.run = Some(QoiOpRun::new()),
+            }
+            if self.run.as_ref().is_some_and(QoiOpRun::is_full) {
+                return [self.finish(), None];
+            }
+            return [None, None];
+        }
+
+        let op = if self.encountered.contains(&rgba) {
+            QoiOps::Index(QoiOpIndex::from_rgba(&rgba))
+        } else if let Some(diff) = QoiOpDiff::from_difference(&self.previous, &rgba) {
+            QoiOps::Diff(diff)
+        } else if let Some(luma) = QoiOpLuma::from_difference(&self.previous, &rgba) {
+            QoiOps::Luma(luma)
+        } else if rgba.a == self.previous.a {
+            QoiOps::RGB(QoiOpRGB::from_rgba(&rgba))
+        } else {
+            QoiOps::RGBA(QoiOpRGBA::from_rgba(&rgba))
+        };
+        // as in `encode_pixels`, run pixels are kept out of the index
+        self.encountered.set(&rgba);
+        self.previous = rgba;
+        [self.finish(), Some(op)]
+    }
+
+    /// Flush the pending run, if any.
+    fn finish(&mut self) -> Option<QoiOps> {
+        self.run.take().map(QoiOps::Run)
+    }
+}
+
+/// Encoder that writes ops to `W` as soon as they are decided, so memory use does not grow with
+/// the image size.
+///
+/// Every op is a separate small write, so wrap unbuffered sinks such as files or sockets in a
+/// [`std::io::BufWriter`].
+#[cfg(feature = "std")]
+#[derive(Debug)]
+pub struct QoiEncoder<W: Write> {
+    writer: W,
+    ops: OpEncoder,
+    channels: usize,
+    expected: usize,  // bytes in the whole image
+    remaining: usize, // pixels still expected before `finish`
+    partial: [u8; 4], // bytes of a pixel split across two `write_pixels` calls
+    partial_len: usize,
+}
+
+#[cfg(feature = "std")]
+impl<W: Write> QoiEncoder<W> {
+    /// Validate the header and write it to `writer`.
+    pub fn new(mut writer: W, header: &QoiHeader) -> Result<Self> {
+        header.validate()?;
+        writer.write_all(&header.to_bytes())?;
+        Ok(Self {
+            writer,
+            ops: OpEncoder::new(),
+            channels: header.channels.bytes_per_pixel(),
+            expected: header.pixel_count() * header.channels.bytes_per_pixel(),
+            remaining: header.pixel_count(),
+            partial: [0; 4],
+            partial_len: 0,
+        })
+    }
+
+    /// Encode the next pixels of the image, e.g. a row at a time. The slice does not have to end
+    /// on a pixel boundary.
+    pub fn write_pixels(&mut self, mut pixels: &[u8]) -> Result<()> {
+        if self.written() + pixels.len() > self.expected {
+            return Err(QoiError::InvalidPixelBuffer {
+                expected: self.expected,
+                actual: self.written() + pixels.len(),
+            });
+        }
+
+        if self.partial_len > 0 {
+            let missing = (self.channels - self.partial_len).min(pixels.len());
+            self.partial[self.partial_len..self.partial_len + missing]
+                .copy_from_slice(&pixels[..missing]);
+            self.partial_len += missing;
+            pixels = &pixels[missing..];
+            if self.partial_len < self.channels {
+                return Ok(());
+            }
+            self.partial_len = 0;
+            self.push(RGBA::from_bytes(&self.partial[..self.channels]))?;
+        }
+
+        let mut chunks = pixels.chunks_exact(self.channels);
+        for pixel in &mut chunks {
+            self.push(RGBA::from_bytes(pixel))?;
+        }
+        let rest = chunks.remainder();
+        self.partial[..rest.len()].copy_from_slice(rest);
+        self.partial_len = rest.len();
+        Ok(())
+    }
+
+    /// Flush the last run and write the end marker, returning the writer. Fails if fewer pixels
+    /// than the header describes were written.
+    pub fn finish(mut self) -> Result<W> {
+        if self.remaining > 0 {
+            return Err(QoiError::InvalidPixelBuffer {
+                expected: self.expected,
+                actual: self.written(),
+            });
+        }
+        if let Some(op) = self.ops.finish() {
+            self.write_op(&op)?;
+        }
+        self.writer.write_all(&QOI_END_MARKER)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Number of pixel bytes received so far.
+    fn written(&self) -> usize {
+        self.expected - self.remaining * self.channels + self.partial_len
+    }
+
+    fn push(&mut self, pixel: RGBA) -> Result<()> {
+        self.remaining -= 1;
+        for op in self.ops.push(pixel).into_iter().flatten() {
+            self.write_op(&op)?;
+        }
+        Ok(())
+    }
+
+    fn write_op(&mut self, op: &QoiOps) -> Result<()> {
+        let mut buf = [0; 5];
+        let size = op.write_bytes(&mut buf);
+        self.writer.write_all(&buf[..size])?;
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{testing, Colorspace};
+
+    /// Encode `pixels`, check that they decode back unchanged and return the text form of every
+    /// op the encoder chose.
+    fn encoded_ops(pixels: &[u8], header: &QoiHeader) -> Vec<String> {
+        let bytes = encode(pixels, header).unwrap();
+        assert_eq!(crate::decode(&bytes).unwrap().1, pixels);
+        crate::ops(&bytes)
+            .unwrap()
+            .map(|info| info.op.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn splits_runs_longer_than_62() {
+        let header = QoiHeader::new(20, 10, Channel::RGBA, Colorspace::SRGB);
+        let pixels = [0x40, 0x50, 0x60, 0xFF].repeat(200);
+        assert_eq!(
+            encoded_ops(&pixels, &header),
+            ["RGB 64 80 96", "RUN 62", "RUN 62", "RUN 62", "RUN 13"]
+        );
+    }
+
+    #[test]
+    fn starts_with_a_run_of_the_implicit_pixel() {
+        let header = QoiHeader::new(3, 1, Channel::RGB, Colorspace::SRGB);
+        let pixels = [0, 0, 0, 0, 0, 0, 9, 9, 9];
+        assert_eq!(encoded_ops(&pixels, &header)[0], "RUN 2");
+    }
+
+    #[test]
+    fn diff_wraps_around() {
+        let header = QoiHeader::new(2, 1, Channel::RGB, Colorspace::SRGB);
+        let pixels = [0xFF, 0x00, 0x01, 0x00, 0xFF, 0x00];
+        assert_eq!(encoded_ops(&pixels, &header)[1], "DIFF dr=1 dg=-1 db=-1");
+    }
+
+    #[test]
+    fn luma_wraps_around() {
+        let header = QoiHeader::new(2, 1, Channel::RGB, Colorspace::SRGB);
+        let pixels = [250, 250, 250, 10, 5, 12];
+        assert_eq!(encoded_ops(&pixels, &header)[1], "LUMA dg=11 dr=16 db=18");
+    }
+
+    #[test]
+    fn encode_does_not_keep_the_worst_case_buffer() {
+        let header = QoiHeader::new(64, 64, Channel::RGBA, Colorspace::SRGB);
+        let bytes = encode(&[0x80; 64 * 64 * 4], &header).unwrap();
+        assert!(bytes.capacity() < max_encoded_size(64, 64, Channel::RGBA) / 4);
+
+        let pixels = testing::image(64, 64, Channel::RGBA, 1);
+        let bytes = encode(&pixels, &header).unwrap();
+        let mut out = vec![0; max_encoded_size(64, 64, Channel::RGBA)];
+        let len = encode_into(&pixels, &header, &mut out).unwrap();
+        assert_eq!(bytes, out[..len]);
+    }
+
+    #[test]
+    fn reuses_indexed_colors() {
+        let header = QoiHeader::new(4, 1, Channel::RGBA, Colorspace::SRGB);
+        let [a, b] = [[0x12, 0x34, 0x56, 0x78], [0xF0, 0x0F, 0xAA, 0x55]];
+        let pixels = [a, b, a, b].concat();
+        let index = |color: [u8; 4]| format!("INDEX {}", RGBA::from(color).hash());
+        assert_eq!(&encoded_ops(&pixels, &header)[2..], [index(a), index(b)]);
+    }
+
+    #[test]
+    fn encode_into_needs_room_for_the_whole_stream() {
+        let header = QoiHeader::new(32, 32, Channel::RGB, Colorspace::SRGB);
+        let pixels = testing::image(32, 32, Channel::RGB, 7);
+        let bytes = encode(&pixels, &header).unwrap();
+
+        let mut out = vec![0; bytes.len()];
+        assert_eq!(
+            encode_into(&pixels, &header, &mut out).unwrap(),
+            bytes.len()
+        );
+        assert_eq!(out, bytes);
+
+        let mut out = vec![0; bytes.len() - 1];
+        let result = encode_into(&pixels, &header, &mut out);
+        assert!(matches!(result, Err(QoiError::OutputTooSmall { len }) if len == bytes.len() - 1));
+    }
+
+    #[test]
+    fn leaves_run_pixels_out_of_the_index() {
+        // the opening run never stores the implicit initial pixel, so qoi.h writes the black
+        // pixel at the end in full rather than as an INDEX
+        let header = QoiHeader::new(4, 1, Channel::RGB, Colorspace::SRGB);
+        let pixels = [0, 0, 0, 0, 0, 0, 200, 50, 10, 0, 0, 0];
+        let bytes = encode(&pixels, &header).unwrap();
+        let listing: Vec<_> = crate::ops(&bytes)
+            .unwrap()
+            .map(|info| info.op.to_string())
+            .collect();
+        assert_eq!(listing, ["RUN 2", "RGB 200 50 10", "RGB 0 0 0"]);
+    }
+
+    #[test]
+    fn all_encoders_agree() {
+        for (width, height) in [(1, 1), (7, 3), (64, 64), (200, 50)] {
+            for channels in [Channel::RGB, Channel::RGBA] {
+                for seed in 0..4 {
+                    let header = QoiHeader::new(width, height, channels, Colorspace::SRGB);
+                    let pixels = testing::image(width, height, channels, seed);
+                    let bytes = encode(&pixels, &header).unwrap();
+
+                    let chunks = encode_chunks(&pixels, &header).unwrap();
+                    assert_eq!(chunks.to_bytes(&header), bytes);
+
+                    // odd chunk sizes, so pixels are split across `write_pixels` calls
+                    let mut encoder = QoiEncoder::new(Vec::new(), &header).unwrap();
+                    let mut rest = &pixels[..];
+                    for size in (1..=17).cycle() {
+                        if rest.is_empty() {
+                            break;
+                        }
+                        let (chunk, tail) = rest.split_at(size.min(rest.len()));
+                        encoder.write_pixels(chunk).unwrap();
+                        rest = tail;
+                    }
+                    assert_eq!(encoder.finish().unwrap(), bytes);
+                }
+            }
+        }
+    }
+}
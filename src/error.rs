@@ -0,0 +1,88 @@
+use core::fmt;
+#[cfg(feature = "std")]
+use std::io;
+
+#[derive(Debug)]
+pub enum QoiError {
+    /// The stream does not start with "qoif".
+    InvalidMagic([u8; 4]),
+    /// Width or height is zero, or the image exceeds the supported pixel count.
+    InvalidDimensions { width: u32, height: u32 },
+    /// The channel byte is neither 3 nor 4.
+    InvalidChannels(u8),
+    /// The colorspace byte is neither 0 nor 1.
+    InvalidColorspace(u8),
+    /// The stream ended before every pixel was decoded.
+    UnexpectedEof,
+    /// The op stream is not followed by the 8-byte end marker.
+    MissingEndMarker,
+    /// The op stream produces more pixels than `width * height`.
+    InvalidPixelCount { expected: usize, actual: usize },
+    /// `len` bytes follow the end marker.
+    TrailingBytes { len: usize },
+    /// Re-encoding the decoded pixels gives a different stream, first differing at `offset`.
+    NotCanonical { offset: usize },
+    /// The pixel buffer length does not match `width * height * channels`.
+    InvalidPixelBuffer { expected: usize, actual: usize },
+    /// The output buffer of `len` bytes cannot hold the encoded image.
+    OutputTooSmall { len: usize },
+    /// A line of assembly text could not be assembled.
+    InvalidAssembly { line: usize, reason: &'static str },
+    /// Reading from or writing to the underlying stream failed.
+    #[cfg(feature = "std")]
+    Io(io::Error),
+}
+
+pub type Result<T> = core::result::Result<T, QoiError>;
+
+impl fmt::Display for QoiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QoiError::InvalidMagic(magic) => write!(f, "invalid magic bytes {magic:02x?}"),
+            QoiError::InvalidDimensions { width, height } => {
+                write!(f, "invalid image dimensions {width}x{height}")
+            }
+            QoiError::InvalidChannels(channels) => write!(f, "invalid channel count {channels}"),
+            QoiError::InvalidColorspace(colorspace) => {
+                write!(f, "invalid colorspace {colorspace}")
+            }
+            QoiError::UnexpectedEof => write!(f, "unexpected end of stream"),
+            QoiError::MissingEndMarker => write!(f, "missing end marker"),
+            QoiError::InvalidPixelCount { expected, actual } => write!(
+                f,
+                "op stream produces {actual} pixels, the header requires {expected}"
+            ),
+            QoiError::TrailingBytes { len } => write!(f, "{len} bytes after the end marker"),
+            QoiError::NotCanonical { offset } => {
+                write!(f, "re-encoding differs from the stream at byte {offset}")
+            }
+            QoiError::InvalidPixelBuffer { expected, actual } => write!(
+                f,
+                "pixel buffer holds {actual} bytes, the header requires {expected}"
+            ),
+            QoiError::OutputTooSmall { len } => {
+                write!(f, "output buffer of {len} bytes is too small")
+            }
+            QoiError::InvalidAssembly { line, reason } => write!(f, "line {line}: {reason}"),
+            #[cfg(feature = "std")]
+            QoiError::Io(error) => write!(f, "i/o error: {error}"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for QoiError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            QoiError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<io::Error> for QoiError {
+    fn from(error: io::Error) -> Self {
+        QoiError::Io(error)
+    }
+}
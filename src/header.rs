@@ -0,0 +1,144 @@
+use core::fmt;
+
+use crate::{QoiError, Result, QOI_HEADER_SIZE, QOI_MAGIC, QOI_PIXELS_MAX};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Channel {
+    RGB = 3,
+    RGBA = 4,
+}
+
+impl Channel {
+    /// Number of bytes a pixel occupies in a packed buffer.
+    pub fn bytes_per_pixel(&self) -> usize {
+        *self as usize
+    }
+}
+
+impl TryFrom<u8> for Channel {
+    type Error = QoiError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            3 => Ok(Channel::RGB),
+            4 => Ok(Channel::RGBA),
+            _ => Err(QoiError::InvalidChannels(value)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Colorspace {
+    SRGB = 0,
+    Linear = 1,
+}
+
+impl TryFrom<u8> for Colorspace {
+    type Error = QoiError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            0 => Ok(Colorspace::SRGB),
+            1 => Ok(Colorspace::Linear),
+            _ => Err(QoiError::InvalidColorspace(value)),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct QoiHeader {
+    pub magic: [char; 4],
+    pub width: u32,
+    pub height: u32,
+    pub channels: Channel,
+    pub colorspace: Colorspace,
+}
+
+impl QoiHeader {
+    pub fn new(width: u32, height: u32, channels: Channel, colorspace: Colorspace) -> Self {
+        Self {
+            magic: QOI_MAGIC,
+            width,
+            height,
+            channels,
+            colorspace,
+        }
+    }
+
+    /// Serialize the header: magic, big-endian width and height, channels and colorspace.
+    pub fn to_bytes(&self) -> [u8; QOI_HEADER_SIZE] {
+        let mut bytes = [0; QOI_HEADER_SIZE];
+        for (byte, c) in bytes.iter_mut().zip(self.magic) {
+            *byte = c as u8;
+        }
+        bytes[4..8].copy_from_slice(&self.width.to_be_bytes());
+        bytes[8..12].copy_from_slice(&self.height.to_be_bytes());
+        bytes[12] = self.channels as u8;
+        bytes[13] = self.colorspace as u8;
+        bytes
+    }
+
+    /// Parse and validate a serialized header.
+    pub fn from_bytes(bytes: &[u8; QOI_HEADER_SIZE]) -> Result<Self> {
+        let magic = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        if magic.map(char::from) != QOI_MAGIC {
+            return Err(QoiError::InvalidMagic(magic));
+        }
+        let header = Self {
+            magic: QOI_MAGIC,
+            width: u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]),
+            height: u32::from_be_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]),
+            channels: Channel::try_from(bytes[12])?,
+            colorspace: Colorspace::try_from(bytes[13])?,
+        };
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Parse the header at the start of a `.qoi` stream without looking at the pixel data, e.g.
+    /// to read the dimensions of a file from only its first 14 bytes.
+    pub fn peek(bytes: &[u8]) -> Result<Self> {
+        let bytes = bytes
+            .get(..QOI_HEADER_SIZE)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or(QoiError::UnexpectedEof)?;
+        Self::from_bytes(bytes)
+    }
+
+    /// Reject a wrong magic, empty images and images too large to be addressed safely.
+    pub fn validate(&self) -> Result<()> {
+        if self.magic != QOI_MAGIC {
+            return Err(QoiError::InvalidMagic(self.magic.map(|c| c as u8)));
+        }
+        let pixels = self.width as u64 * self.height as u64;
+        if pixels == 0 || pixels > QOI_PIXELS_MAX as u64 {
+            return Err(QoiError::InvalidDimensions {
+                width: self.width,
+                height: self.height,
+            });
+        }
+        Ok(())
+    }
+
+    /// Number of pixels described by the header.
+    pub fn pixel_count(&self) -> usize {
+        self.width as usize * self.height as usize
+    }
+}
+
+/// The header line of the assembly text read by `assemble`, e.g. `QOIF 800 600 4 srgb`.
+impl fmt::Display for QoiHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let colorspace = match self.colorspace {
+            Colorspace::SRGB => "srgb",
+            Colorspace::Linear => "linear",
+        };
+        write!(
+            f,
+            "QOIF {} {} {} {colorspace}",
+            self.width,
+            self.height,
+            self.channels.bytes_per_pixel()
+        )
+    }
+}
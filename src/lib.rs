@@ -0,0 +1,66 @@
+//! Encoder and decoder for the [QOI](https://qoiformat.org/) image format.
+//!
+//! ```
+//! # #[cfg(feature = "alloc")] {
+//! use qoi::{Channel, Colorspace, QoiHeader};
+//!
+//! let pixels = [0xAF, 0xAF, 0xAF, 0xFF].repeat(12);
+//! let header = QoiHeader::new(4, 3, Channel::RGBA, Colorspace::SRGB);
+//!
+//! let bytes = qoi::encode(&pixels, &header).unwrap();
+//! let (decoded_header, decoded) = qoi::decode(&bytes).unwrap();
+//! assert_eq!(decoded_header.width, 4);
+//! assert_eq!(decoded, pixels);
+//! # }
+//! ```
+//!
+//! The codec core works without the standard library. Disable the default `std` feature for
+//! `no_std` targets, and enable `alloc` to keep the `Vec`-returning functions.
+#![cfg_attr(not(feature = "std"), no_std)]
+#![allow(clippy::upper_case_acronyms)]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+mod asm;
+mod decode;
+mod encode;
+mod error;
+mod header;
+mod ops;
+mod pixel;
+#[cfg(all(test, feature = "std"))]
+mod testing;
+mod verify;
+
+#[cfg(feature = "alloc")]
+pub use asm::assemble;
+#[cfg(feature = "alloc")]
+pub use decode::decode;
+#[cfg(feature = "std")]
+pub use decode::QoiDecoder;
+pub use decode::{
+    decode_into, decode_into_channels, ops, pixels, DecodeStatus, OpInfo, Ops, Pixels,
+    QoiPushDecoder,
+};
+#[cfg(feature = "std")]
+pub use encode::QoiEncoder;
+#[cfg(feature = "alloc")]
+pub use encode::{encode, encode_chunks};
+pub use encode::{encode_into, max_encoded_size};
+pub use error::{QoiError, Result};
+pub use header::{Channel, Colorspace, QoiHeader};
+#[cfg(feature = "alloc")]
+pub use ops::Chunks;
+pub use ops::{QoiOpDiff, QoiOpIndex, QoiOpLuma, QoiOpRGB, QoiOpRGBA, QoiOpRun, QoiOps, Tag};
+pub use pixel::{Encountered, RGBA};
+pub use verify::verify;
+#[cfg(feature = "alloc")]
+pub use verify::verify_canonical;
+
+pub const QOI_MAGIC: [char; 4] = ['q', 'o', 'i', 'f'];
+pub const QOI_HEADER_SIZE: usize = 14;
+pub const QOI_END_MARKER: [u8; 8] = [0, 0, 0, 0, 0, 0, 0, 1];
+/// Upper bound on `width * height`, matching the reference implementation.
+pub const QOI_PIXELS_MAX: usize = 400_000_000;
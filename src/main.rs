@@ -1,284 +1,656 @@
-use std::fmt;
+//! `qoi`: convert between QOI and PPM/PAM images, and inspect QOI streams.
+
+mod pnm;
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Read, Write};
+use std::process::ExitCode;
+
+use qoi::{Channel, Colorspace, QoiHeader, QoiOps, QOI_END_MARKER, RGBA};
+
+const USAGE: &str = "\
+usage: qoi encode [--channels 3|4] [--colorspace srgb|linear] <in.ppm> <out.qoi>
+       qoi decode [--channels 3|4] <in.qoi> <out.ppm>
+       qoi info <in.qoi>
+       qoi dump [--range x0,y0..x1,y1] <in.qoi>
+       qoi asm <in.txt> <out.qoi>
+       qoi verify [--canonical] <in.qoi>...
+
+Images are read as binary PPM (P6) or PAM (P7). Decoding writes PPM for 3 channels and PAM
+for 4. Use - as a path to read from stdin or write to stdout.
+
+dump lists every op with its byte offset, the first pixel it produces and that pixel's color.
+--range limits the listing to ops producing pixels inside the rectangle, corners included.
+asm turns a listing in the same syntax back into a QOI stream.
+
+verify checks that each stream is well formed: a valid header, exactly width * height pixels,
+the end marker and no trailing bytes. --canonical also requires that re-encoding the pixels
+reproduces the stream byte for byte; the encoder makes the same choices as the reference
+qoi.h, so its output passes. The exit code is 1 if any stream fails.";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let command = match Command::parse(&args) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("qoi: {message}\n\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    match command.run() {
+        Ok(()) => ExitCode::SUCCESS,
+        // the reader went away, e.g. `qoi dump image.qoi | head`
+        Err(error)
+            if error
+                .downcast_ref::<io::Error>()
+                .is_some_and(|error| error.kind() == io::ErrorKind::BrokenPipe) =>
+        {
+            ExitCode::SUCCESS
+        }
+        Err(error) => {
+            eprintln!("qoi: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
 
 #[derive(Debug)]
-enum Channel {
-    RGB = 3,
-    RGBA = 4,
+enum Command {
+    Encode {
+        input: String,
+        output: String,
+        channels: Option<Channel>,
+        colorspace: Option<Colorspace>,
+    },
+    Decode {
+        input: String,
+        output: String,
+        channels: Option<Channel>,
+    },
+    Info {
+        input: String,
+    },
+    Dump {
+        input: String,
+        range: Option<Region>,
+    },
+    Asm {
+        input: String,
+        output: String,
+    },
+    Verify {
+        inputs: Vec<String>,
+        canonical: bool,
+    },
+    Help,
 }
 
-#[derive(Debug, PartialEq, Clone, Copy)]
-struct RGBA {
-    r: u8,
-    g: u8,
-    b: u8,
-    a: u8,
-}
+impl Command {
+    /// Parse the arguments following the program name, returning a usage error message on
+    /// failure.
+    fn parse(args: &[String]) -> Result<Self, String> {
+        let Some((command, args)) = args.split_first() else {
+            return Err("missing command".to_string());
+        };
+
+        let mut paths = Vec::new();
+        let mut given = Vec::new(); // options seen, checked against the command below
+        let mut channels = None;
+        let mut colorspace = None;
+        let mut range = None;
+        let mut canonical = false;
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            // accept both `--flag value` and `--flag=value`
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if arg.starts_with("--") => (flag, Some(value)),
+                _ => (arg.as_str(), None),
+            };
+            let mut value = || {
+                inline
+                    .or_else(|| args.next().map(String::as_str))
+                    .ok_or_else(|| format!("{flag} requires a value"))
+            };
+            match flag {
+                "--channels" => channels = Some(parse_channels(value()?)?),
+                "--colorspace" => colorspace = Some(parse_colorspace(value()?)?),
+                "--range" => range = Some(Region::parse(value()?)?),
+                "--canonical" if inline.is_none() => canonical = true,
+                "-h" | "--help" => return Ok(Command::Help),
+                _ if flag.starts_with('-') && flag != "-" => {
+                    return Err(format!("unknown option {flag}"))
+                }
+                _ => {
+                    paths.push(arg.clone());
+                    continue;
+                }
+            }
+            given.push(flag);
+        }
 
-impl RGBA {
-    fn new() -> Self {
-        Self {
-            r: 0,
-            g: 0,
-            b: 0,
-            a: 0xFF,
+        let allowed: &[&str] = match command.as_str() {
+            "encode" => &["--channels", "--colorspace"],
+            "decode" => &["--channels"],
+            "dump" => &["--range"],
+            "verify" => &["--canonical"],
+            _ => &[],
+        };
+        if let Some(flag) = given.iter().find(|flag| !allowed.contains(flag)) {
+            return Err(format!("{command} does not take {flag}"));
         }
-    }
 
-    fn hash(&self) -> u8 {
-        let index = self.r as u32 * 3 + self.g as u32 * 5 + self.b as u32 * 7 + self.a as u32 * 11;
-        return (index % 64) as u8;
+        let input_output = |paths: Vec<String>| match <[String; 2]>::try_from(paths) {
+            Ok([input, output]) => Ok((input, output)),
+            Err(_) => Err(format!("{command} takes an input and an output path")),
+        };
+        let input = |paths: Vec<String>| match <[String; 1]>::try_from(paths) {
+            Ok([input]) => Ok(input),
+            Err(_) => Err(format!("{command} takes an input path")),
+        };
+        match command.as_str() {
+            "encode" => {
+                let (input, output) = input_output(paths)?;
+                Ok(Command::Encode {
+                    input,
+                    output,
+                    channels,
+                    colorspace,
+                })
+            }
+            "decode" => {
+                let (input, output) = input_output(paths)?;
+                Ok(Command::Decode {
+                    input,
+                    output,
+                    channels,
+                })
+            }
+            "info" => Ok(Command::Info {
+                input: input(paths)?,
+            }),
+            "asm" => {
+                let (input, output) = input_output(paths)?;
+                Ok(Command::Asm { input, output })
+            }
+            "verify" if !paths.is_empty() => Ok(Command::Verify {
+                inputs: paths,
+                canonical,
+            }),
+            "verify" => Err("verify takes one or more input paths".to_string()),
+            "dump" => Ok(Command::Dump {
+                input: input(paths)?,
+                range,
+            }),
+            "help" | "-h" | "--help" => Ok(Command::Help),
+            _ => Err(format!("unknown command {command}")),
+        }
     }
-}
 
-impl fmt::Display for RGBA {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(
-            f,
-            "r: {}, g: {}, b: {}, a: {}",
-            self.r, self.g, self.b, self.a
-        )
+    fn run(self) -> Result<(), Box<dyn Error>> {
+        match self {
+            Command::Encode {
+                input,
+                output,
+                channels,
+                colorspace,
+            } => {
+                let image = pnm::read(&read_input(&input)?)?;
+                let channels = channels.unwrap_or(image.channels);
+                let colorspace = colorspace.unwrap_or(Colorspace::SRGB);
+                let header = QoiHeader::new(image.width, image.height, channels, colorspace);
+                let pixels = convert_channels(&image.pixels, image.channels, channels);
+                let bytes = qoi::encode(&pixels, &header)?;
+                write_output(&output, |out| out.write_all(&bytes))
+            }
+            Command::Decode {
+                input,
+                output,
+                channels,
+            } => {
+                let bytes = read_input(&input)?;
+                let header = QoiHeader::peek(&bytes)?;
+                let channels = channels.unwrap_or(header.channels);
+                let mut pixels = vec![0; header.pixel_count() * channels.bytes_per_pixel()];
+                qoi::decode_into_channels(&bytes, &mut pixels, channels)?;
+                write_output(&output, |out| {
+                    pnm::write(out, header.width, header.height, channels, &pixels)
+                })
+            }
+            Command::Info { input } => {
+                let bytes = read_input(&input)?;
+                print_info(&bytes, &mut io::stdout().lock())
+            }
+            Command::Dump { input, range } => {
+                let bytes = read_input(&input)?;
+                let mut out = BufWriter::new(io::stdout().lock());
+                print_dump(&bytes, range, &mut out)?;
+                out.flush()?;
+                Ok(())
+            }
+            Command::Asm { input, output } => {
+                let text = String::from_utf8(read_input(&input)?)
+                    .map_err(|_| format!("{input}: not valid UTF-8"))?;
+                let bytes = qoi::assemble(&text).map_err(|error| format!("{input}: {error}"))?;
+                write_output(&output, |out| out.write_all(&bytes))
+            }
+            Command::Verify { inputs, canonical } => {
+                let verify = if canonical {
+                    qoi::verify_canonical
+                } else {
+                    qoi::verify
+                };
+                let mut failed = 0;
+                for input in &inputs {
+                    // read errors already name the file
+                    let result = match read_input(input) {
+                        Ok(bytes) => verify(&bytes).map_err(|error| format!("{input}: {error}")),
+                        Err(error) => Err(error.to_string()),
+                    };
+                    match result {
+                        Ok(_) => println!("{input}: ok"),
+                        Err(message) => {
+                            println!("{message}");
+                            failed += 1;
+                        }
+                    }
+                }
+                if failed > 0 {
+                    return Err(format!("{failed} of {} streams failed", inputs.len()).into());
+                }
+                Ok(())
+            }
+            Command::Help => {
+                writeln!(io::stdout(), "{USAGE}")?;
+                Ok(())
+            }
+        }
     }
 }
 
-impl From<u32> for RGBA {
-    fn from(value: u32) -> Self {
-        const BYTE_SIZE: u8 = 8;
-        let r = ((value >> 0 * BYTE_SIZE) & 0xFF) as u8;
-        let g = ((value >> 1 * BYTE_SIZE) & 0xFF) as u8;
-        let b = ((value >> 2 * BYTE_SIZE) & 0xFF) as u8;
-        let a = ((value >> 3 * BYTE_SIZE) & 0xFF) as u8;
-        Self { r, g, b, a }
+/// Print the header, overall compression and a breakdown of the ops in `bytes`.
+fn print_info(bytes: &[u8], out: &mut impl Write) -> Result<(), Box<dyn Error>> {
+    const NAMES: [&str; 6] = ["RUN", "INDEX", "DIFF", "LUMA", "RGB", "RGBA"];
+
+    let header = QoiHeader::peek(bytes)?;
+    let mut ops = qoi::ops(bytes)?;
+    // count, bytes and pixels per op type, in the order of `NAMES`
+    let mut stats = [(0usize, 0usize, 0usize); 6];
+    for info in &mut ops {
+        let kind = match info.op {
+            QoiOps::Run(_) => 0,
+            QoiOps::Index(_) => 1,
+            QoiOps::Diff(_) => 2,
+            QoiOps::Luma(_) => 3,
+            QoiOps::RGB(_) => 4,
+            QoiOps::RGBA(_) => 5,
+        };
+        stats[kind].0 += 1;
+        stats[kind].1 += info.size;
+        stats[kind].2 += info.pixel_count();
     }
-}
 
-impl From<&u32> for RGBA {
-    fn from(value: &u32) -> Self {
-        const BYTE_SIZE: u8 = 8;
-        let r = ((value >> 0 * BYTE_SIZE) & 0xFF) as u8;
-        let g = ((value >> 1 * BYTE_SIZE) & 0xFF) as u8;
-        let b = ((value >> 2 * BYTE_SIZE) & 0xFF) as u8;
-        let a = ((value >> 3 * BYTE_SIZE) & 0xFF) as u8;
-        Self { r, g, b, a }
+    let pixels = header.pixel_count();
+    let raw = pixels * header.channels.bytes_per_pixel();
+    let size = bytes.len();
+    writeln!(out, "dimensions:  {}x{}", header.width, header.height)?;
+    writeln!(
+        out,
+        "channels:    {} ({:?})",
+        header.channels.bytes_per_pixel(),
+        header.channels
+    )?;
+    writeln!(out, "colorspace:  {:?}", header.colorspace)?;
+    writeln!(out, "file size:   {size} bytes")?;
+    writeln!(out, "bits/pixel:  {:.3}", size as f64 * 8.0 / pixels as f64)?;
+    writeln!(
+        out,
+        "ratio:       {:.2}% of {raw} raw bytes",
+        size as f64 / raw as f64 * 100.0
+    )?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{:<6} {:>10} {:>12} {:>7} {:>12}",
+        "op", "count", "bytes", "share", "pixels"
+    )?;
+    for (name, (count, op_bytes, op_pixels)) in NAMES.iter().zip(stats) {
+        writeln!(
+            out,
+            "{name:<6} {count:>10} {op_bytes:>12} {:>6.2}% {op_pixels:>12}",
+            op_bytes as f64 / size as f64 * 100.0
+        )?;
     }
-}
-#[derive(Debug)]
-enum Colorspace {
-    SRGB = 0,
-    Linear = 1,
-}
 
-#[derive(Debug)]
-struct QoiHeader {
-    magic: [char; 4],
-    width: u32,
-    height: u32,
-    channels: Channel,
-    colorspace: Colorspace,
-}
-
-#[derive(Debug, Clone, Copy)]
-enum Tag {
-    B11,
-    B00,
-    B01,
-    B10,
-    B11111110,
-    B11111111,
-}
-
-#[derive(Debug)]
-struct QoiOpRun {
-    tag: Tag, // 2-bit tag b11
-    run: u8,  // 6-bit run-length repeating the previous pixel: 1..62
+    if ops.pixels_decoded() < pixels {
+        return Err(format!(
+            "stream ends after {} of {pixels} pixels",
+            ops.pixels_decoded()
+        )
+        .into());
+    }
+    Ok(())
 }
 
-impl QoiOpRun {
-    fn new() -> Self {
-        Self {
-            tag: Tag::B11,
-            run: 1,
+/// Print every op in the syntax read by the assembler, followed by a comment giving its byte
+/// offset, the position of the first pixel it produces and the resulting color. Ops producing
+/// no pixel inside `range` are skipped.
+fn print_dump(
+    bytes: &[u8],
+    range: Option<Region>,
+    out: &mut impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let header = QoiHeader::peek(bytes)?;
+    let width = header.width as usize;
+    writeln!(out, "{:<28}# {} bytes", header.to_string(), bytes.len())?;
+
+    let mut ops = qoi::ops(bytes)?;
+    for info in &mut ops {
+        let pixels = info.pixel..info.pixel + info.pixel_count();
+        if let Some(range) = &range {
+            if !pixels
+                .clone()
+                .any(|pixel| range.contains(pixel % width, pixel / width))
+            {
+                continue;
+            }
         }
+        let RGBA { r, g, b, a } = info.rgba;
+        writeln!(
+            out,
+            "{:<28}# @{} ({}, {}) rgba({r}, {g}, {b}, {a})",
+            info.op.to_string(),
+            info.offset,
+            info.pixel % width,
+            info.pixel / width,
+        )?;
     }
 
-    fn add_run(&mut self) {
-        self.run += 1;
+    if ops.pixels_decoded() < header.pixel_count() {
+        return Err(format!(
+            "stream ends after {} of {} pixels",
+            ops.pixels_decoded(),
+            header.pixel_count()
+        )
+        .into());
     }
-}
-
-#[derive(Debug)]
-struct QoiOpIndex {
-    tag: Tag,  // 2-bit tag b00
-    index: u8, // 6-bit index into the color index array: 0..63
-}
-
-impl QoiOpIndex {
-    fn new() -> Self {
-        Self {
-            tag: Tag::B00,
-            index: 0,
+    // anything after the ops is listed as raw bytes so the dump assembles back to the input
+    let rest = match ops.remainder().strip_prefix(&QOI_END_MARKER) {
+        Some(rest) => {
+            writeln!(out, "END")?;
+            rest
         }
-    }
-
-    fn from_rgba(color: &RGBA) -> Self {
-        Self {
-            tag: Tag::B00,
-            index: color.hash(),
+        None => {
+            writeln!(out, "# missing end marker")?;
+            ops.remainder()
         }
+    };
+    for chunk in rest.chunks(16) {
+        let bytes: Vec<String> = chunk.iter().map(|byte| format!("0x{byte:02x}")).collect();
+        writeln!(out, "BYTES {}", bytes.join(" "))?;
     }
+    Ok(())
 }
 
-#[derive(Debug)]
-struct QoiOpDiff {
-    tag: Tag, // 2-bit tag b01
-    dr: u8,   // 2-bit   red channel difference from the previous pixel between -2..1
-    dg: u8,   // 2-bit green channel difference from the previous pixel between -2..1
-    db: u8,   // 2-bit  blue channel difference from the previous pixel between -2..1
-}
-
-#[derive(Debug)]
-struct QoiOpLuma {
-    tag: Tag,  // 2-bit tag b10
-    dg: u8,    // 6-bit green channel difference from the previous pixel -32..31
-    dr_dg: u8, // 4-bit   red channel difference minus green channel difference -8..7
-    dr_db: u8, // 4-bit  blue channel difference minus green channel difference -8..7
-}
-
-#[derive(Debug)]
-struct QoiOpRGB {
-    tag: Tag,  // 8-bit tag b11111110
-    red: u8,   // 8-bit   red channel value
-    green: u8, // 8-bit green channel value
-    blue: u8,  // 8-bit  blue channel value
+/// Rectangle of pixels given as `x0,y0..x1,y1`, both corners included.
+#[derive(Debug, Clone, Copy)]
+struct Region {
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
 }
 
-#[derive(Debug)]
-struct QoiOpRGBA {
-    tag: Tag,  // 8-bit tag b11111111
-    red: u8,   // 8-bit   red channel value
-    green: u8, // 8-bit green channel value
-    blue: u8,  // 8-bit  blue channel value
-    alpha: u8, // 8-bit alpha channel value
-}
+impl Region {
+    fn parse(value: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid range {value}, expected x0,y0..x1,y1");
+        let point = |point: &str| -> Option<(usize, usize)> {
+            let (x, y) = point.split_once(',')?;
+            Some((x.trim().parse().ok()?, y.trim().parse().ok()?))
+        };
+        let (start, end) = value.split_once("..").ok_or_else(invalid)?;
+        let (x0, y0) = point(start).ok_or_else(invalid)?;
+        let (x1, y1) = point(end).ok_or_else(invalid)?;
+        Ok(Self {
+            x0: x0.min(x1),
+            y0: y0.min(y1),
+            x1: x0.max(x1),
+            y1: y0.max(y1),
+        })
+    }
 
-impl QoiOpRGBA {
-    fn from_rgba(color: &RGBA) -> Self {
-        Self {
-            tag: Tag::B11111111,
-            red: color.r,
-            green: color.g,
-            blue: color.b,
-            alpha: color.a,
-        }
+    fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x0..=self.x1).contains(&x) && (self.y0..=self.y1).contains(&y)
     }
 }
 
-#[derive(Debug)]
-enum QoiOps {
-    Run(QoiOpRun),
-    Index(QoiOpIndex),
-    Diff(QoiOpDiff),
-    Luma(QoiOpLuma),
-    RGB(QoiOpRGB),
-    RGBA(QoiOpRGBA),
+fn parse_channels(value: &str) -> Result<Channel, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "3" | "rgb" => Ok(Channel::RGB),
+        "4" | "rgba" => Ok(Channel::RGBA),
+        _ => Err(format!("invalid channel count {value}, expected 3 or 4")),
+    }
 }
 
-#[derive(Debug)]
-struct Encountered([RGBA; 64]);
-
-impl Encountered {
-    fn new() -> Self {
-        Self([RGBA::new(); 64])
+fn parse_colorspace(value: &str) -> Result<Colorspace, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "0" | "srgb" => Ok(Colorspace::SRGB),
+        "1" | "linear" => Ok(Colorspace::Linear),
+        _ => Err(format!(
+            "invalid colorspace {value}, expected srgb or linear"
+        )),
     }
+}
 
-    fn contains(&self, color: &RGBA) -> bool {
-        self.0.contains(color)
+/// Read the whole of `path`, or of stdin for `-`.
+fn read_input(path: &str) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        return Ok(bytes);
     }
+    fs::read(path).map_err(|error| io::Error::new(error.kind(), format!("{path}: {error}")))
+}
 
-    fn set(&mut self, color: &RGBA) {
-        let index = color.hash() as usize;
-        self.0[index] = *color;
-    }
+/// Run `write` against a buffered `path`, or stdout for `-`.
+fn write_output(
+    path: &str,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> Result<(), Box<dyn Error>> {
+    let out: Box<dyn Write> = if path == "-" {
+        Box::new(io::stdout().lock())
+    } else {
+        let file = File::create(path)
+            .map_err(|error| io::Error::new(error.kind(), format!("{path}: {error}")))?;
+        Box::new(file)
+    };
+    let mut out = BufWriter::new(out);
+    write(&mut out)?;
+    out.flush()?;
+    Ok(())
 }
 
-impl fmt::Display for Encountered {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut content = String::new();
-        content += "Encountered colors:";
-        for (idx, c) in self.0.iter().enumerate() {
-            if !(c == &RGBA::new()) {
-                content += &format!("\n{idx}: [{c}]")
-            }
-        }
-        write!(f, "{}", content)
+/// Add an opaque alpha channel or drop the existing one so `pixels` have `to` channels.
+fn convert_channels(pixels: &[u8], from: Channel, to: Channel) -> Vec<u8> {
+    if from == to {
+        return pixels.to_vec();
+    }
+    let count = pixels.len() / from.bytes_per_pixel();
+    let mut converted = Vec::with_capacity(count * to.bytes_per_pixel());
+    for pixel in pixels.chunks_exact(from.bytes_per_pixel()) {
+        let rgba = [pixel[0], pixel[1], pixel[2], 0xFF];
+        converted.extend_from_slice(&rgba[..to.bytes_per_pixel()]);
     }
+    converted
 }
 
-struct Chunks(Vec<QoiOps>);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Chunks {
-    fn new() -> Self {
-        Self(Vec::new())
+    fn parse(args: &str) -> Result<Command, String> {
+        let args: Vec<String> = args.split_whitespace().map(String::from).collect();
+        Command::parse(&args)
     }
 
-    fn last_mut(&mut self) -> Option<&mut QoiOps> {
-        self.0.last_mut()
+    #[test]
+    fn parses_flags_in_either_form() {
+        for args in [
+            "encode --channels=4 --colorspace linear in.ppm out.qoi",
+            "encode in.ppm --colorspace=linear out.qoi --channels 4",
+        ] {
+            let Ok(Command::Encode {
+                input,
+                output,
+                channels,
+                colorspace,
+            }) = parse(args)
+            else {
+                panic!("{args}");
+            };
+            assert_eq!((input.as_str(), output.as_str()), ("in.ppm", "out.qoi"));
+            assert_eq!(channels, Some(Channel::RGBA));
+            assert_eq!(colorspace, Some(Colorspace::Linear));
+        }
     }
 
-    fn push(&mut self, op: QoiOps) {
-        self.0.push(op)
+    #[test]
+    fn takes_dash_as_a_path() {
+        let Ok(Command::Decode { input, output, .. }) = parse("decode - -") else {
+            panic!();
+        };
+        assert_eq!((input.as_str(), output.as_str()), ("-", "-"));
     }
-}
 
-impl fmt::Display for Chunks {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> fmt::Result {
-        let mut content = String::new();
-        content += "Chunks:";
-        for v in self.0.iter() {
-            let display = match v {
-                QoiOps::Run(run) => format!("{run:?}"),
-                QoiOps::Index(index) => format!("{index:?}"),
-                QoiOps::RGBA(rgba) => format!("{rgba:?}"),
-                _ => todo!(),
-            };
-            content += "\n";
-            content += &display;
+    #[test]
+    fn rejects_bad_arguments() {
+        for (args, message) in [
+            (
+                "decode --colorspace srgb in.qoi out.ppm",
+                "decode does not take --colorspace",
+            ),
+            ("info --canonical in.qoi", "info does not take --canonical"),
+            (
+                "verify --canonical=yes in.qoi",
+                "unknown option --canonical",
+            ),
+            (
+                "encode in.ppm out.qoi --channels",
+                "--channels requires a value",
+            ),
+            (
+                "encode --channels=5 in.ppm out.qoi",
+                "invalid channel count 5, expected 3 or 4",
+            ),
+            ("dump --verbose in.qoi", "unknown option --verbose"),
+            ("info a.qoi b.qoi", "info takes an input path"),
+            ("compress in.ppm", "unknown command compress"),
+        ] {
+            assert_eq!(parse(args).unwrap_err(), message, "{args}");
         }
+    }
 
-        write!(f, "{}", content)
+    #[test]
+    fn info_counts_every_op_type() {
+        let bytes = qoi::assemble(
+            "QOIF 5 2 4 srgb\n\
+             RGBA 10 20 30 255\n\
+             RUN 3\n\
+             DIFF dr=1 dg=0 db=-1\n\
+             LUMA dg=10 dr=10 db=10\n\
+             INDEX 5\n\
+             RGB 1 2 3\n\
+             RUN 2\n",
+        )
+        .unwrap();
+        let mut out = Vec::new();
+        print_info(&bytes, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\
+dimensions:  5x2
+channels:    4 (RGBA)
+colorspace:  SRGB
+file size:   37 bytes
+bits/pixel:  29.600
+ratio:       92.50% of 40 raw bytes
+
+op          count        bytes   share       pixels
+RUN             2            2   5.41%            5
+INDEX           1            1   2.70%            1
+DIFF            1            1   2.70%            1
+LUMA            1            2   5.41%            1
+RGB             1            4  10.81%            1
+RGBA            1            5  13.51%            1
+"
+        );
+
+        // drop the last run along with the end marker
+        let error = print_info(&bytes[..bytes.len() - 9], &mut Vec::new()).unwrap_err();
+        assert_eq!(error.to_string(), "stream ends after 8 of 10 pixels");
     }
-}
 
-fn main() {
-    let pixels: [u32; 12] = [
-        0xFFAFAFAF, 0xFFAFAFAF, 0xFFAFAFAF, 0xFFAAAFAF, 0xFFAFAFAF, 0xFFAFAFAF, 0xFFAFAFAF,
-        0xFFAFAFAF, 0xFFAFAFAF, 0xFFAFAFAF, 0xFFAFAFAF, 0xFFAFAFAF,
-    ];
-
-    let mut chunks = Chunks::new();
-    let mut encountered = Encountered::new();
-
-    let mut previous = RGBA::new();
-    for pixel in pixels.iter() {
-        let rgba = RGBA::from(pixel);
-        // dbg!(&rgba);
-        if rgba == previous {
-            if let Some(QOI) = chunks.last_mut() {
-                match QOI {
-                    QoiOps::Run(chunk) => chunk.add_run(),
-                    _ => chunks.push(QoiOps::Run(QoiOpRun::new())),
-                }
-            }
-        } else if encountered.contains(&rgba) {
-            chunks.push(QoiOps::Index(QoiOpIndex::from_rgba(&rgba)));
-            previous = rgba;
-        } else {
-            let index = rgba.hash() as usize;
-            encountered.set(&rgba);
-            chunks.push(QoiOps::RGBA(QoiOpRGBA::from_rgba(&rgba)));
-            previous = rgba;
-        }
+    #[test]
+    fn dump_lists_only_ops_inside_the_range() {
+        // the first run covers (2, 0) to (1, 1), the second (3, 1) to (3, 2)
+        let bytes = qoi::assemble(
+            "QOIF 4 3 3 srgb\n\
+             RGB 1 1 1\n\
+             DIFF dr=1 dg=1 db=1\n\
+             RUN 4\n\
+             RGB 9 9 9\n\
+             RUN 5\n",
+        )
+        .unwrap();
+        let dump = |range: &str| {
+            let mut out = Vec::new();
+            print_dump(&bytes, Some(Region::parse(range).unwrap()), &mut out).unwrap();
+            // the op column of every listed op
+            String::from_utf8(out)
+                .unwrap()
+                .lines()
+                .skip(1)
+                .map(|line| line.split('#').next().unwrap().trim_end().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(dump("0,1..1,1"), ["RUN 4", "END"]);
+        // corners may be given in either order
+        assert_eq!(dump("2,1..1,1"), ["RUN 4", "RGB 9 9 9", "END"]);
+        assert_eq!(dump("3,0..3,2"), ["RUN 4", "RUN 5", "END"]);
+        assert_eq!(dump("0,0..0,0"), ["RGB 1 1 1", "END"]);
+        assert_eq!(dump("9,9..9,9"), ["END"]);
+        assert!(Region::parse("1,1").is_err());
+
+        let mut out = Vec::new();
+        print_dump(&bytes, Some(Region::parse("1,1..2,1").unwrap()), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("RUN 4                       # @19 (2, 0) rgba(2, 2, 2, 255)\n"));
     }
 
-    println!("{}", chunks);
-    println!("{}", encountered)
+    #[test]
+    fn dump_assembles_back_to_the_input() {
+        // runs (including one opening the image), repeats and steps of every size
+        let pixels: Vec<u8> = (0u32..48 * 16)
+            .flat_map(|i| {
+                let (x, y) = (i % 48, i / 48);
+                let step = (x / 6 * y) as u8;
+                [
+                    step,
+                    step.wrapping_mul(3),
+                    (x / 3) as u8,
+                    0xFF - (y as u8 & 4) * 8,
+                ]
+            })
+            .collect();
+        let header = QoiHeader::new(48, 16, Channel::RGBA, Colorspace::SRGB);
+        let mut bytes = qoi::encode(&pixels, &header).unwrap();
+        bytes.extend_from_slice(&[1, 2, 3]);
+
+        let mut listing = Vec::new();
+        print_dump(&bytes, None, &mut listing).unwrap();
+        let listing = String::from_utf8(listing).unwrap();
+        assert_eq!(qoi::assemble(&listing).unwrap(), bytes, "{listing}");
+    }
 }
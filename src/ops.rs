@@ -0,0 +1,551 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::RGBA;
+#[cfg(feature = "alloc")]
+use crate::{QoiHeader, QOI_END_MARKER, QOI_HEADER_SIZE};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Tag {
+    B11,
+    B00,
+    B01,
+    B10,
+    B11111110,
+    B11111111,
+}
+
+impl Tag {
+    pub fn bits(&self) -> u8 {
+        match self {
+            Tag::B00 => 0b00000000,
+            Tag::B01 => 0b01000000,
+            Tag::B10 => 0b10000000,
+            Tag::B11 => 0b11000000,
+            Tag::B11111110 => 0b11111110,
+            Tag::B11111111 => 0b11111111,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Self {
+        // the 8-bit tags take precedence over the 2-bit tags
+        match byte {
+            0b11111110 => Tag::B11111110,
+            0b11111111 => Tag::B11111111,
+            _ => match byte >> 6 {
+                0b00 => Tag::B00,
+                0b01 => Tag::B01,
+                0b10 => Tag::B10,
+                _ => Tag::B11,
+            },
+        }
+    }
+
+    /// Number of bytes in an op starting with this tag, including the tag byte.
+    pub fn op_len(&self) -> usize {
+        match self {
+            Tag::B00 | Tag::B01 | Tag::B11 => 1,
+            Tag::B10 => 2,
+            Tag::B11111110 => 4,
+            Tag::B11111111 => 5,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct QoiOpRun {
+    pub(crate) tag: Tag, // 2-bit tag b11
+    pub(crate) run: u8,  // 6-bit run-length repeating the previous pixel: 1..62
+}
+
+impl QoiOpRun {
+    // 63 and 64 would collide with the QOI_OP_RGB and QOI_OP_RGBA tags
+    pub const MAX_RUN: u8 = 62;
+
+    pub fn new() -> Self {
+        Self {
+            tag: Tag::B11,
+            run: 1,
+        }
+    }
+
+    /// A run of `run` pixels, or `None` unless `run` is in 1..=62.
+    pub fn with_run(run: u8) -> Option<Self> {
+        (1..=Self::MAX_RUN)
+            .contains(&run)
+            .then_some(Self { tag: Tag::B11, run })
+    }
+
+    /// Number of pixels in the run: 1..=62.
+    pub fn run(&self) -> u8 {
+        self.run
+    }
+
+    /// Whether the run has reached the 62 pixels a single op can hold.
+    pub fn is_full(&self) -> bool {
+        self.run >= Self::MAX_RUN
+    }
+
+    /// Extend the run by one pixel.
+    ///
+    /// Panics if the run is already full.
+    pub fn add_run(&mut self) {
+        assert!(
+            !self.is_full(),
+            "a run holds at most {} pixels",
+            Self::MAX_RUN
+        );
+        self.run += 1;
+    }
+}
+
+impl Default for QoiOpRun {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub struct QoiOpIndex {
+    pub(crate) tag: Tag,  // 2-bit tag b00
+    pub(crate) index: u8, // 6-bit index into the color index array: 0..63
+}
+
+impl QoiOpIndex {
+    /// An op reading slot `index`, or `None` unless `index` is in 0..=63.
+    pub fn new(index: u8) -> Option<Self> {
+        (index < 64).then_some(Self {
+            tag: Tag::B00,
+            index,
+        })
+    }
+
+    pub fn from_rgba(color: &RGBA) -> Self {
+        Self {
+            tag: Tag::B00,
+            index: color.hash(),
+        }
+    }
+
+    pub fn index(&self) -> u8 {
+        self.index
+    }
+}
+
+#[derive(Debug)]
+pub struct QoiOpDiff {
+    pub(crate) tag: Tag, // 2-bit tag b01
+    pub(crate) dr: u8,   // 2-bit   red channel difference from the previous pixel between -2..1
+    pub(crate) dg: u8,   // 2-bit green channel difference from the previous pixel between -2..1
+    pub(crate) db: u8,   // 2-bit  blue channel difference from the previous pixel between -2..1
+}
+
+impl QoiOpDiff {
+    pub fn from_difference(previous: &RGBA, color: &RGBA) -> Option<Self> {
+        if color.a != previous.a {
+            return None;
+        }
+        Self::from_differences(channel_differences(previous, color))
+    }
+
+    /// An op for the unbiased `[dr, dg, db]` channel differences, or `None` unless each is in
+    /// -2..=1.
+    pub fn from_differences([dr, dg, db]: [i8; 3]) -> Option<Self> {
+        if [dr, dg, db].iter().any(|d| !(-2..=1).contains(d)) {
+            return None;
+        }
+        Some(Self {
+            tag: Tag::B01,
+            dr: (dr + 2) as u8,
+            dg: (dg + 2) as u8,
+            db: (db + 2) as u8,
+        })
+    }
+
+    pub fn apply(&self, previous: &RGBA) -> RGBA {
+        // differences are stored with a bias of 2
+        RGBA {
+            r: previous.r.wrapping_add(self.dr).wrapping_sub(2),
+            g: previous.g.wrapping_add(self.dg).wrapping_sub(2),
+            b: previous.b.wrapping_add(self.db).wrapping_sub(2),
+            a: previous.a,
+        }
+    }
+
+    /// The unbiased `[dr, dg, db]` channel differences.
+    pub fn differences(&self) -> [i8; 3] {
+        [self.dr, self.dg, self.db].map(|d| d as i8 - 2)
+    }
+}
+
+#[derive(Debug)]
+pub struct QoiOpLuma {
+    pub(crate) tag: Tag,  // 2-bit tag b10
+    pub(crate) dg: u8,    // 6-bit green channel difference from the previous pixel -32..31
+    pub(crate) dr_dg: u8, // 4-bit   red channel difference minus green channel difference -8..7
+    pub(crate) db_dg: u8, // 4-bit  blue channel difference minus green channel difference -8..7
+}
+
+impl QoiOpLuma {
+    pub fn from_difference(previous: &RGBA, color: &RGBA) -> Option<Self> {
+        if color.a != previous.a {
+            return None;
+        }
+        Self::from_differences(channel_differences(previous, color))
+    }
+
+    /// An op for the unbiased `[dr, dg, db]` channel differences, or `None` unless `dg` is in
+    /// -32..=31 and `dr - dg` and `db - dg` are in -8..=7.
+    pub fn from_differences([dr, dg, db]: [i8; 3]) -> Option<Self> {
+        let dr_dg = dr.wrapping_sub(dg);
+        let db_dg = db.wrapping_sub(dg);
+        if !(-32..=31).contains(&dg) || [dr_dg, db_dg].iter().any(|d| !(-8..=7).contains(d)) {
+            return None;
+        }
+        Some(Self {
+            tag: Tag::B10,
+            dg: (dg + 32) as u8,
+            dr_dg: (dr_dg + 8) as u8,
+            db_dg: (db_dg + 8) as u8,
+        })
+    }
+
+    pub fn apply(&self, previous: &RGBA) -> RGBA {
+        // the green difference is stored with a bias of 32, the other two with a bias of 8
+        let dg = self.dg.wrapping_sub(32);
+        RGBA {
+            r: previous
+                .r
+                .wrapping_add(dg)
+                .wrapping_add(self.dr_dg)
+                .wrapping_sub(8),
+            g: previous.g.wrapping_add(dg),
+            b: previous
+                .b
+                .wrapping_add(dg)
+                .wrapping_add(self.db_dg)
+                .wrapping_sub(8),
+            a: previous.a,
+        }
+    }
+
+    /// The unbiased `[dr, dg, db]` channel differences, with the green difference added back to
+    /// the red and blue ones.
+    pub fn differences(&self) -> [i8; 3] {
+        let dg = self.dg as i8 - 32;
+        [dg + self.dr_dg as i8 - 8, dg, dg + self.db_dg as i8 - 8]
+    }
+}
+
+/// Per-channel `[dr, dg, db]` from `previous` to `color`, wrapping around like the decoder.
+fn channel_differences(previous: &RGBA, color: &RGBA) -> [i8; 3] {
+    [
+        color.r.wrapping_sub(previous.r) as i8,
+        color.g.wrapping_sub(previous.g) as i8,
+        color.b.wrapping_sub(previous.b) as i8,
+    ]
+}
+
+#[derive(Debug)]
+pub struct QoiOpRGB {
+    pub(crate) tag: Tag,  // 8-bit tag b11111110
+    pub(crate) red: u8,   // 8-bit   red channel value
+    pub(crate) green: u8, // 8-bit green channel value
+    pub(crate) blue: u8,  // 8-bit  blue channel value
+}
+
+impl QoiOpRGB {
+    pub fn from_rgba(color: &RGBA) -> Self {
+        Self {
+            tag: Tag::B11111110,
+            red: color.r,
+            green: color.g,
+            blue: color.b,
+        }
+    }
+
+    pub fn apply(&self, previous: &RGBA) -> RGBA {
+        RGBA {
+            r: self.red,
+            g: self.green,
+            b: self.blue,
+            a: previous.a,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct QoiOpRGBA {
+    pub(crate) tag: Tag,  // 8-bit tag b11111111
+    pub(crate) red: u8,   // 8-bit   red channel value
+    pub(crate) green: u8, // 8-bit green channel value
+    pub(crate) blue: u8,  // 8-bit  blue channel value
+    pub(crate) alpha: u8, // 8-bit alpha channel value
+}
+
+impl QoiOpRGBA {
+    pub fn from_rgba(color: &RGBA) -> Self {
+        Self {
+            tag: Tag::B11111111,
+            red: color.r,
+            green: color.g,
+            blue: color.b,
+            alpha: color.a,
+        }
+    }
+
+    pub fn to_rgba(&self) -> RGBA {
+        RGBA {
+            r: self.red,
+            g: self.green,
+            b: self.blue,
+            a: self.alpha,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum QoiOps {
+    Run(QoiOpRun),
+    Index(QoiOpIndex),
+    Diff(QoiOpDiff),
+    Luma(QoiOpLuma),
+    RGB(QoiOpRGB),
+    RGBA(QoiOpRGBA),
+}
+
+impl QoiOps {
+    /// Read the op at the start of `bytes` and return it with the number of bytes it used.
+    pub fn from_bytes(bytes: &[u8]) -> Option<(Self, usize)> {
+        let first = *bytes.first()?;
+        let tag = Tag::from_byte(first);
+        let op = match tag {
+            Tag::B00 => (
+                QoiOps::Index(QoiOpIndex {
+                    tag,
+                    index: first & 0x3F,
+                }),
+                1,
+            ),
+            Tag::B01 => {
+                let op = QoiOpDiff {
+                    tag,
+                    dr: (first >> 4) & 0x03,
+                    dg: (first >> 2) & 0x03,
+                    db: first & 0x03,
+                };
+                (QoiOps::Diff(op), 1)
+            }
+            Tag::B10 => {
+                let second = *bytes.get(1)?;
+                let op = QoiOpLuma {
+                    tag,
+                    dg: first & 0x3F,
+                    dr_dg: second >> 4,
+                    db_dg: second & 0x0F,
+                };
+                (QoiOps::Luma(op), 2)
+            }
+            Tag::B11 => (
+                QoiOps::Run(QoiOpRun {
+                    tag,
+                    run: (first & 0x3F) + 1,
+                }),
+                1,
+            ),
+            Tag::B11111110 => {
+                let &[red, green, blue] = bytes.get(1..4)? else {
+                    return None;
+                };
+                (
+                    QoiOps::RGB(QoiOpRGB {
+                        tag,
+                        red,
+                        green,
+                        blue,
+                    }),
+                    4,
+                )
+            }
+            Tag::B11111111 => {
+                let &[red, green, blue, alpha] = bytes.get(1..5)? else {
+                    return None;
+                };
+                let op = QoiOpRGBA {
+                    tag,
+                    red,
+                    green,
+                    blue,
+                    alpha,
+                };
+                (QoiOps::RGBA(op), 5)
+            }
+        };
+        Some(op)
+    }
+
+    /// Write the op into `out` and return the number of bytes used (1..=5).
+    pub fn write_bytes(&self, out: &mut [u8]) -> usize {
+        match self {
+            QoiOps::Run(op) => {
+                // stored with a bias of -1, so a run of 1..62 becomes 0..61
+                out[0] = op.tag.bits() | (op.run - 1);
+                1
+            }
+            QoiOps::Index(op) => {
+                out[0] = op.tag.bits() | op.index;
+                1
+            }
+            QoiOps::Diff(op) => {
+                out[0] = op.tag.bits() | op.dr << 4 | op.dg << 2 | op.db;
+                1
+            }
+            QoiOps::Luma(op) => {
+                out[0] = op.tag.bits() | op.dg;
+                out[1] = op.dr_dg << 4 | op.db_dg;
+                2
+            }
+            QoiOps::RGB(op) => {
+                out[..4].copy_from_slice(&[op.tag.bits(), op.red, op.green, op.blue]);
+                4
+            }
+            QoiOps::RGBA(op) => {
+                out[..5].copy_from_slice(&[op.tag.bits(), op.red, op.green, op.blue, op.alpha]);
+                5
+            }
+        }
+    }
+}
+
+/// One op per line in the text form read by the assembler, e.g. `RUN 12`, `INDEX 53`,
+/// `DIFF dr=-1 dg=0 db=1`, `LUMA dg=-3 dr=1 db=0` or `RGB 255 128 0`. Differences are the
+/// unbiased per-channel differences from the previous pixel.
+impl fmt::Display for QoiOps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QoiOps::Run(op) => write!(f, "RUN {}", op.run),
+            QoiOps::Index(op) => write!(f, "INDEX {}", op.index),
+            QoiOps::Diff(op) => {
+                let [dr, dg, db] = op.differences();
+                write!(f, "DIFF dr={dr} dg={dg} db={db}")
+            }
+            QoiOps::Luma(op) => {
+                let [dr, dg, db] = op.differences();
+                write!(f, "LUMA dg={dg} dr={dr} db={db}")
+            }
+            QoiOps::RGB(op) => write!(f, "RGB {} {} {}", op.red, op.green, op.blue),
+            QoiOps::RGBA(op) => {
+                write!(f, "RGBA {} {} {} {}", op.red, op.green, op.blue, op.alpha)
+            }
+        }
+    }
+}
+
+/// The ops making up an image, in stream order.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct Chunks(Vec<QoiOps>);
+
+#[cfg(feature = "alloc")]
+impl Chunks {
+    pub fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    pub fn ops(&self) -> &[QoiOps] {
+        &self.0
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut QoiOps> {
+        self.0.last_mut()
+    }
+
+    pub fn push(&mut self, op: QoiOps) {
+        self.0.push(op)
+    }
+
+    /// Serialize the header, every op and the end marker into a `.qoi` byte stream.
+    pub fn to_bytes(&self, header: &QoiHeader) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(QOI_HEADER_SIZE + self.0.len() + QOI_END_MARKER.len());
+        bytes.extend_from_slice(&header.to_bytes());
+
+        let mut buf = [0; 5];
+        for op in self.0.iter() {
+            let size = op.write_bytes(&mut buf);
+            bytes.extend_from_slice(&buf[..size]);
+        }
+
+        bytes.extend_from_slice(&QOI_END_MARKER);
+        bytes
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Default for Chunks {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for Chunks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Chunks:")?;
+        for v in self.0.iter() {
+            write!(f, "\n{v}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn constructors_reject_fields_that_do_not_fit() {
+        assert!(QoiOpRun::with_run(0).is_none());
+        assert!(QoiOpRun::with_run(QoiOpRun::MAX_RUN + 1).is_none());
+        assert!(QoiOpIndex::new(64).is_none());
+        assert!(QoiOpDiff::from_differences([2, 0, 0]).is_none());
+        assert!(QoiOpLuma::from_differences([0, 32, 0]).is_none());
+        assert!(QoiOpLuma::from_differences([-9, 0, 0]).is_none());
+    }
+
+    #[test]
+    #[should_panic]
+    fn full_run_cannot_grow() {
+        QoiOpRun::with_run(QoiOpRun::MAX_RUN).unwrap().add_run();
+    }
+
+    #[test]
+    fn every_valid_op_survives_serialization() {
+        let mut ops = Vec::new();
+        ops.extend(
+            (1..=QoiOpRun::MAX_RUN)
+                .filter_map(QoiOpRun::with_run)
+                .map(QoiOps::Run),
+        );
+        ops.extend((0..64).filter_map(QoiOpIndex::new).map(QoiOps::Index));
+        for dg in -32..=31 {
+            for d in -8..=7 {
+                let luma = QoiOpLuma::from_differences([dg + d, dg, dg - d / 2]).unwrap();
+                ops.push(QoiOps::Luma(luma));
+            }
+        }
+        let diffs = (-2..=1).flat_map(|d| QoiOpDiff::from_differences([d, -d / 2, 1]));
+        ops.extend(diffs.map(QoiOps::Diff));
+        let color = RGBA::from([1, 2, 3, 4]);
+        ops.push(QoiOps::RGB(QoiOpRGB::from_rgba(&color)));
+        ops.push(QoiOps::RGBA(QoiOpRGBA::from_rgba(&color)));
+
+        for op in ops {
+            let mut buf = [0; 5];
+            let size = op.write_bytes(&mut buf);
+            let (parsed, parsed_size) = QoiOps::from_bytes(&buf[..size]).unwrap();
+            assert_eq!(parsed_size, size);
+            assert_eq!(parsed.to_string(), op.to_string());
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use core::fmt;
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RGBA {
+    pub r: u8,
+    pub g: u8,
+    pub b: u8,
+    pub a: u8,
+}
+
+impl RGBA {
+    /// The pixel every stream implicitly starts from.
+    pub fn new() -> Self {
+        Self {
+            r: 0,
+            g: 0,
+            b: 0,
+            a: 0xFF,
+        }
+    }
+
+    /// Position of the color in the color index array.
+    pub fn hash(&self) -> u8 {
+        hash([self.r, self.g, self.b, self.a]) as u8
+    }
+
+    /// Read a pixel from a 3-byte (alpha implied 255) or 4-byte slice.
+    #[cfg(feature = "alloc")]
+    pub(crate) fn from_bytes(bytes: &[u8]) -> Self {
+        match *bytes {
+            [r, g, b] => Self { r, g, b, a: 0xFF },
+            [r, g, b, a] => Self { r, g, b, a },
+            _ => panic!("a pixel is 3 or 4 bytes, got {}", bytes.len()),
+        }
+    }
+}
+
+impl Default for RGBA {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for RGBA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "r: {}, g: {}, b: {}, a: {}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+impl From<u32> for RGBA {
+    fn from(value: u32) -> Self {
+        let [r, g, b, a] = value.to_le_bytes();
+        Self { r, g, b, a }
+    }
+}
+
+impl From<&u32> for RGBA {
+    fn from(value: &u32) -> Self {
+        Self::from(*value)
+    }
+}
+
+impl From<[u8; 4]> for RGBA {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Self { r, g, b, a }
+    }
+}
+
+impl From<RGBA> for [u8; 4] {
+    fn from(color: RGBA) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+/// Position of an `[r, g, b, a]` color in the color index array, for the hot loops that keep
+/// pixels as plain bytes.
+#[inline]
+pub(crate) fn hash([r, g, b, a]: [u8; 4]) -> usize {
+    (r as usize * 3 + g as usize * 5 + b as usize * 7 + a as usize * 11) % 64
+}
+
+/// The 64-entry color index array shared by the encoder and decoder.
+#[derive(Debug)]
+pub struct Encountered([RGBA; 64]);
+
+impl Encountered {
+    // the color index array starts out zero-initialized, including the alpha channel
+    const EMPTY: RGBA = RGBA {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: 0,
+    };
+
+    pub fn new() -> Self {
+        Self([Self::EMPTY; 64])
+    }
+
+    /// Only the slot the color hashes to is checked, as that is the slot a decoder will read.
+    pub fn contains(&self, color: &RGBA) -> bool {
+        self.0[color.hash() as usize] == *color
+    }
+
+    /// Only the low 6 bits of `index` are used, as in a QOI_OP_INDEX byte.
+    pub fn get(&self, index: u8) -> RGBA {
+        self.0[(index & 0x3F) as usize]
+    }
+
+    pub fn set(&mut self, color: &RGBA) {
+        let index = color.hash() as usize;
+        self.0[index] = *color;
+    }
+}
+
+impl Default for Encountered {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for Encountered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Encountered colors:")?;
+        for (idx, c) in self.0.iter().enumerate() {
+            if !(c == &Self::EMPTY) {
+                write!(f, "\n{idx}: [{c}]")?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,266 @@
+//! Reading and writing the binary Netpbm formats used by the command-line tool: PPM (`P6`) for
+//! RGB images and PAM (`P7`) for RGB and RGBA images. Only 8-bit samples are supported.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Write};
+
+use qoi::Channel;
+
+#[derive(Debug)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub channels: Channel,
+    pub pixels: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub enum PnmError {
+    /// The file starts with neither "P6" nor "P7".
+    UnsupportedFormat,
+    /// A header field is missing or not a number.
+    InvalidHeader(&'static str),
+    /// Samples wider than 8 bits.
+    UnsupportedMaxval(u32),
+    /// A PAM depth other than 3 (RGB) or 4 (RGB_ALPHA).
+    UnsupportedDepth(u32),
+    /// The raster holds fewer bytes than the header describes.
+    Truncated { expected: usize, actual: usize },
+}
+
+impl fmt::Display for PnmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PnmError::UnsupportedFormat => write!(f, "not a binary PPM (P6) or PAM (P7) image"),
+            PnmError::InvalidHeader(field) => write!(f, "invalid or missing {field} in header"),
+            PnmError::UnsupportedMaxval(maxval) => {
+                write!(f, "unsupported maxval {maxval}, only 255 is supported")
+            }
+            PnmError::UnsupportedDepth(depth) => {
+                write!(f, "unsupported depth {depth}, only 3 and 4 are supported")
+            }
+            PnmError::Truncated { expected, actual } => {
+                write!(
+                    f,
+                    "raster holds {actual} bytes, the header requires {expected}"
+                )
+            }
+        }
+    }
+}
+
+impl Error for PnmError {}
+
+/// Parse a PPM or PAM image. Bytes after the raster are ignored.
+pub fn read(bytes: &[u8]) -> Result<Image, PnmError> {
+    let mut header = Header { bytes, pos: 2 };
+    let (width, height, channels) = match bytes.get(..2) {
+        Some(b"P6") => {
+            let width = header.number("width")?;
+            let height = header.number("height")?;
+            check_maxval(header.number("maxval")?)?;
+            // a single whitespace byte separates the header from the raster
+            header.pos += 1;
+            (width, height, Channel::RGB)
+        }
+        Some(b"P7") => header.pam()?,
+        _ => return Err(PnmError::UnsupportedFormat),
+    };
+
+    let expected = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|count| count.checked_mul(channels.bytes_per_pixel()))
+        .ok_or(PnmError::InvalidHeader("dimensions"))?;
+    let raster = bytes.get(header.pos..).unwrap_or_default();
+    if raster.len() < expected {
+        return Err(PnmError::Truncated {
+            expected,
+            actual: raster.len(),
+        });
+    }
+
+    Ok(Image {
+        width,
+        height,
+        channels,
+        pixels: raster[..expected].to_vec(),
+    })
+}
+
+/// Write `pixels` as PPM if they are RGB, or as PAM if they are RGBA.
+pub fn write(
+    out: &mut (impl Write + ?Sized),
+    width: u32,
+    height: u32,
+    channels: Channel,
+    pixels: &[u8],
+) -> io::Result<()> {
+    match channels {
+        Channel::RGB => write!(out, "P6\n{width} {height}\n255\n")?,
+        Channel::RGBA => write!(
+            out,
+            "P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH 4\nMAXVAL 255\nTUPLTYPE RGB_ALPHA\nENDHDR\n"
+        )?,
+    }
+    out.write_all(pixels)
+}
+
+fn check_maxval(maxval: u32) -> Result<(), PnmError> {
+    if maxval != 255 {
+        return Err(PnmError::UnsupportedMaxval(maxval));
+    }
+    Ok(())
+}
+
+/// Cursor over the text part of a Netpbm header.
+struct Header<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Header<'_> {
+    /// Next whitespace-separated token, skipping `#` comments.
+    fn token(&mut self) -> Option<&str> {
+        loop {
+            match self.bytes.get(self.pos)? {
+                b'#' => {
+                    while self.bytes.get(self.pos).is_some_and(|&b| b != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                b if b.is_ascii_whitespace() => self.pos += 1,
+                _ => break,
+            }
+        }
+        let start = self.pos;
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| !b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.pos]).ok()
+    }
+
+    fn number(&mut self, field: &'static str) -> Result<u32, PnmError> {
+        self.token()
+            .and_then(|token| token.parse().ok())
+            .ok_or(PnmError::InvalidHeader(field))
+    }
+
+    /// Parse the `KEY value` lines of a PAM header up to and including `ENDHDR`.
+    fn pam(&mut self) -> Result<(u32, u32, Channel), PnmError> {
+        let (mut width, mut height, mut depth) = (None, None, None);
+        loop {
+            match self.token().ok_or(PnmError::InvalidHeader("ENDHDR"))? {
+                "WIDTH" => width = Some(self.number("width")?),
+                "HEIGHT" => height = Some(self.number("height")?),
+                "DEPTH" => depth = Some(self.number("depth")?),
+                "MAXVAL" => check_maxval(self.number("maxval")?)?,
+                // the depth alone decides the layout
+                "TUPLTYPE" => {
+                    self.token();
+                }
+                "ENDHDR" => break,
+                _ => return Err(PnmError::InvalidHeader("header line")),
+            }
+        }
+        // skip the newline ending the ENDHDR line
+        self.pos += 1;
+
+        let channels = match depth.ok_or(PnmError::InvalidHeader("depth"))? {
+            3 => Channel::RGB,
+            4 => Channel::RGBA,
+            depth => return Err(PnmError::UnsupportedDepth(depth)),
+        };
+        Ok((
+            width.ok_or(PnmError::InvalidHeader("width"))?,
+            height.ok_or(PnmError::InvalidHeader("height"))?,
+            channels,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(channels: Channel, pixels: &[u8]) {
+        let mut bytes = Vec::new();
+        write(&mut bytes, 3, 2, channels, pixels).unwrap();
+        let image = read(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (3, 2));
+        assert_eq!(image.channels, channels);
+        assert_eq!(image.pixels, pixels);
+    }
+
+    #[test]
+    fn reads_ppm_with_comments() {
+        let mut bytes = b"P6\n# written by hand\n2 1 # width and height\n255\n".to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7]);
+        let image = read(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.channels, Channel::RGB);
+        assert_eq!(image.pixels, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn reads_pam() {
+        let mut bytes =
+            b"P7\nWIDTH 1\nHEIGHT 2\nDEPTH 3\nMAXVAL 255\nTUPLTYPE RGB\nENDHDR\n".to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6]);
+        let image = read(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (1, 2));
+        assert_eq!(image.channels, Channel::RGB);
+        assert_eq!(image.pixels, [1, 2, 3, 4, 5, 6]);
+
+        // fields may come in any order
+        let mut bytes =
+            b"P7\nTUPLTYPE RGB_ALPHA\nDEPTH 4\nMAXVAL 255\nHEIGHT 1\nWIDTH 2\nENDHDR\n".to_vec();
+        bytes.extend_from_slice(&[1, 2, 3, 4, 5, 6, 7, 8]);
+        let image = read(&bytes).unwrap();
+        assert_eq!((image.width, image.height), (2, 1));
+        assert_eq!(image.channels, Channel::RGBA);
+        assert_eq!(image.pixels, [1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    #[test]
+    fn rejects_bad_headers() {
+        let pam = |fields: &str| read(format!("P7\n{fields}\nENDHDR\n").as_bytes());
+        assert!(matches!(
+            pam("WIDTH 4294967295\nHEIGHT 4294967295\nDEPTH 4\nMAXVAL 255"),
+            Err(PnmError::InvalidHeader("dimensions"))
+        ));
+        assert!(matches!(
+            pam("WIDTH 1\nHEIGHT 1\nDEPTH 2\nMAXVAL 255"),
+            Err(PnmError::UnsupportedDepth(2))
+        ));
+        assert!(matches!(
+            pam("WIDTH 1\nHEIGHT 1\nDEPTH 3\nMAXVAL 65535"),
+            Err(PnmError::UnsupportedMaxval(65535))
+        ));
+        assert!(matches!(
+            pam("HEIGHT 1\nDEPTH 3\nMAXVAL 255"),
+            Err(PnmError::InvalidHeader("width"))
+        ));
+        assert!(matches!(
+            read(b"P3\n1 1\n255\n"),
+            Err(PnmError::UnsupportedFormat)
+        ));
+        assert!(matches!(
+            read(b"P6\n2 1\n255\n\x01\x02\x03\x04\x05"),
+            Err(PnmError::Truncated {
+                expected: 6,
+                actual: 5
+            })
+        ));
+    }
+
+    #[test]
+    fn round_trips_through_write() {
+        round_trip(Channel::RGB, &[7; 3 * 2 * 3]);
+        round_trip(Channel::RGBA, &(0..3 * 2 * 4).collect::<Vec<u8>>());
+    }
+}
@@ -0,0 +1,57 @@
+//! Generated images shared by the unit tests.
+
+use crate::Channel;
+
+/// A deterministic image mixing every situation the encoder distinguishes: runs (some longer
+/// than 62 pixels), colors recurring from a small palette, small and medium steps that wrap
+/// around 0 and 255, jumps to unrelated colors and, for RGBA, changes in alpha.
+pub fn image(width: u32, height: u32, channels: Channel, seed: u32) -> Vec<u8> {
+    let palette = [
+        [0x10, 0x20, 0x30, 0xFF],
+        [0xF0, 0x80, 0x00, 0xFF],
+        [0x00, 0x00, 0x00, 0x80],
+        [0xFF, 0xFF, 0xFF, 0x00],
+    ];
+    let mut state = seed.wrapping_mul(0x9E37_79B9) | 1;
+    let mut next = move || {
+        // xorshift32
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        state
+    };
+
+    let count = (width * height) as usize;
+    let mut px = [0u8, 0, 0, 0xFF];
+    let mut repeat = 0;
+    let mut pixels = Vec::with_capacity(count * channels.bytes_per_pixel());
+    for _ in 0..count {
+        if repeat > 0 {
+            repeat -= 1;
+        } else {
+            let r = next();
+            match r % 8 {
+                0 => repeat = r % 150,
+                1 => px = palette[(r >> 8) as usize % palette.len()],
+                2 | 3 => {
+                    for (channel, step) in px.iter_mut().zip(r.to_le_bytes()).take(3) {
+                        *channel = channel.wrapping_add(step % 4).wrapping_sub(2);
+                    }
+                }
+                4 | 5 => {
+                    let dg = (r % 64) as u8;
+                    px[0] = px[0].wrapping_add(dg).wrapping_add((r >> 8) as u8 % 16);
+                    px[1] = px[1].wrapping_add(dg).wrapping_sub(32);
+                    px[2] = px[2].wrapping_add(dg).wrapping_sub((r >> 16) as u8 % 16);
+                }
+                6 => px[..3].copy_from_slice(&r.to_le_bytes()[..3]),
+                _ => px[3] = (r >> 24) as u8,
+            }
+        }
+        if channels == Channel::RGB {
+            px[3] = 0xFF;
+        }
+        pixels.extend_from_slice(&px[..channels.bytes_per_pixel()]);
+    }
+    pixels
+}
@@ -0,0 +1,135 @@
+use crate::{ops, QoiError, QoiHeader, Result, QOI_END_MARKER};
+
+/// Check that `bytes` is a well-formed `.qoi` stream: a valid header, ops producing exactly
+/// `width * height` pixels, the end marker, and nothing after it.
+///
+/// This is stricter than [`decode_into`](crate::decode_into), which ignores whatever follows the
+/// end marker and the unused part of a run overshooting the last pixel.
+pub fn verify(bytes: &[u8]) -> Result<QoiHeader> {
+    let header = QoiHeader::peek(bytes)?;
+    let mut ops = ops(bytes)?;
+    ops.by_ref().for_each(drop);
+
+    let expected = header.pixel_count();
+    let actual = ops.pixels_decoded();
+    if actual < expected {
+        return Err(QoiError::UnexpectedEof);
+    }
+    if actual > expected {
+        return Err(QoiError::InvalidPixelCount { expected, actual });
+    }
+
+    let rest = ops
+        .remainder()
+        .strip_prefix(&QOI_END_MARKER)
+        .ok_or(QoiError::MissingEndMarker)?;
+    if !rest.is_empty() {
+        return Err(QoiError::TrailingBytes { len: rest.len() });
+    }
+    Ok(header)
+}
+
+/// Like [`verify`], and also check that encoding the decoded pixels with this crate's encoder
+/// reproduces `bytes` exactly.
+///
+/// The encoder chooses ops the way the reference `qoi.h` does, down to leaving run pixels out of
+/// the index, so its output passes too. Encoders are free to choose differently, so a stream
+/// failing this check is still valid; it was just written by a different encoder or by hand.
+#[cfg(feature = "alloc")]
+pub fn verify_canonical(bytes: &[u8]) -> Result<QoiHeader> {
+    let header = verify(bytes)?;
+    let (_, pixels) = crate::decode(bytes)?;
+    let encoded = crate::encode(&pixels, &header)?;
+    if let Some(offset) =
+        (0..bytes.len().max(encoded.len())).find(|&offset| bytes.get(offset) != encoded.get(offset))
+    {
+        return Err(QoiError::NotCanonical { offset });
+    }
+    Ok(header)
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{Channel, Colorspace};
+
+    /// A 2x1 RGB stream: the header, then `ops`, then `tail` in place of the end marker.
+    fn stream(ops: &[u8], tail: &[u8]) -> Vec<u8> {
+        let header = QoiHeader::new(2, 1, Channel::RGB, Colorspace::SRGB);
+        let mut bytes = header.to_bytes().to_vec();
+        bytes.extend_from_slice(ops);
+        bytes.extend_from_slice(tail);
+        bytes
+    }
+
+    #[test]
+    fn accepts_a_well_formed_stream() {
+        let header = verify(&stream(&[0xFE, 1, 2, 3, 0xC0], &QOI_END_MARKER)).unwrap();
+        assert_eq!((header.width, header.height), (2, 1));
+    }
+
+    #[test]
+    fn rejects_a_bad_header() {
+        let bytes = stream(&[0xFE, 1, 2, 3, 0xC0], &QOI_END_MARKER);
+        let with = |offset: usize, value| {
+            let mut bytes = bytes.clone();
+            bytes[offset] = value;
+            verify(&bytes)
+        };
+        assert!(matches!(with(0, b'Q'), Err(QoiError::InvalidMagic(_))));
+        assert!(matches!(
+            with(7, 0),
+            Err(QoiError::InvalidDimensions { width: 0, .. })
+        ));
+        assert!(matches!(with(12, 5), Err(QoiError::InvalidChannels(5))));
+        assert!(matches!(with(13, 2), Err(QoiError::InvalidColorspace(2))));
+        assert!(matches!(verify(&bytes[..10]), Err(QoiError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn rejects_a_bad_op_stream() {
+        assert!(matches!(
+            verify(&stream(&[0xFE, 1, 2], &[])),
+            Err(QoiError::UnexpectedEof)
+        ));
+        // the marker is there, it is the second pixel that is missing
+        assert!(matches!(
+            verify(&stream(&[0xFE, 1, 2, 3], &QOI_END_MARKER)),
+            Err(QoiError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            verify(&stream(&[0xFE, 1, 2, 3, 0xC1], &QOI_END_MARKER)),
+            Err(QoiError::InvalidPixelCount {
+                expected: 2,
+                actual: 3
+            })
+        ));
+        assert!(matches!(
+            verify(&stream(&[0xFE, 1, 2, 3, 0xC0], &QOI_END_MARKER[..7])),
+            Err(QoiError::MissingEndMarker)
+        ));
+        assert!(matches!(
+            verify(&stream(
+                &[0xFE, 1, 2, 3, 0xC0],
+                &[QOI_END_MARKER, [0; 8]].concat()
+            )),
+            Err(QoiError::TrailingBytes { len: 8 })
+        ));
+    }
+
+    #[test]
+    fn canonical_means_what_the_encoder_writes() {
+        let header = QoiHeader::new(64, 64, Channel::RGBA, Colorspace::SRGB);
+        let bytes =
+            crate::encode(&crate::testing::image(64, 64, Channel::RGBA, 2), &header).unwrap();
+        assert!(verify_canonical(&bytes).is_ok());
+
+        // the same pixels, with an RGBA op where an RGB op would do
+        let bytes = stream(&[0xFF, 1, 2, 3, 0xFF, 0xC0], &QOI_END_MARKER);
+        assert!(verify(&bytes).is_ok());
+        assert!(matches!(
+            verify_canonical(&bytes),
+            Err(QoiError::NotCanonical { offset: 14 })
+        ));
+    }
+}